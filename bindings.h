@@ -2,10 +2,17 @@
 // xmlsec1 headers include
 //
 #include <xmlsec/app.h>
+#include <xmlsec/base64.h>
 #include <xmlsec/crypto.h>
+#include <xmlsec/errors.h>
 #include <xmlsec/keys.h>
 #include <xmlsec/templates.h>
 #include <xmlsec/transforms.h>
 #include <xmlsec/xmldsig.h>
 #include <xmlsec/xmlsec.h>
 #include <xmlsec/xmltree.h>
+
+//
+// backend specific headers not pulled in by xmlsec/crypto.h
+//
+#include <xmlsec/openssl/evp.h>
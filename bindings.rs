@@ -13,6 +13,10 @@ use std::process::Command;
 
 const BINDINGS: &str = "bindings.rs";
 
+// first OpenSSL release with the EVP_PKEY_get_*, EVP_PKEY_eq and EVP_PKEY_CTX_new_from_name APIs and the error code
+// layout the wrapper relies upon
+const OPENSSL_MIN_VERSION: &str = "3.0.0";
+
 
 fn main()
 {
@@ -22,6 +26,12 @@ fn main()
     println!("cargo:rustc-link-lib=ssl");              // -lssl
     println!("cargo:rustc-link-lib=crypto");           // -lcrypto
 
+    PkgConfig::new()
+        .atleast_version(OPENSSL_MIN_VERSION)
+        .cargo_metadata(false)
+        .probe("libcrypto")
+        .unwrap_or_else(|e| panic!("xmlsec requires OpenSSL {} or later: {}", OPENSSL_MIN_VERSION, e));
+
     let path_out      = PathBuf::from(env::var("OUT_DIR").unwrap());
     let path_bindings = path_out.join(BINDINGS);
 
@@ -15,3 +15,4 @@
 
 mod openssl;
 pub use openssl::XmlSecSignatureMethod;
+pub use openssl::XmlSecKeyKind;
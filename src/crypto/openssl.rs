@@ -3,9 +3,12 @@
 //!
 use crate::bindings;
 
+use crate::transforms::transform_href;
+
 
 /// Supported digesting and signing methods as specified by the XML standard.
 #[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum XmlSecSignatureMethod
 {
     Aes128Cbc,
@@ -54,6 +57,62 @@ pub enum XmlSecSignatureMethod
 
 impl XmlSecSignatureMethod
 {
+    const ALL: [Self; 38] = [
+        Self::Aes128Cbc,
+        Self::Aes192Cbc,
+        Self::Aes256Cbc,
+        Self::KWAes128,
+        Self::KWAes192,
+        Self::KWAes256,
+        Self::Des3Cbc,
+        Self::KWDes3,
+        Self::DsaSha1,
+        Self::DsaSha256,
+        Self::EcdsaSha1,
+        Self::EcdsaSha224,
+        Self::EcdsaSha256,
+        Self::EcdsaSha384,
+        Self::EcdsaSha512,
+        Self::HmacMd5,
+        Self::HmacRipemd160,
+        Self::HmacSha1,
+        Self::HmacSha224,
+        Self::HmacSha256,
+        Self::HmacSha384,
+        Self::HmacSha512,
+        Self::Md5,
+        Self::Ripemd160,
+        Self::RsaMd5,
+        Self::RsaRipemd160,
+        Self::RsaSha1,
+        Self::RsaSha224,
+        Self::RsaSha256,
+        Self::RsaSha384,
+        Self::RsaSha512,
+        Self::RsaPkcs1,
+        Self::RsaOaep,
+        Self::Sha1,
+        Self::Sha224,
+        Self::Sha256,
+        Self::Sha384,
+        Self::Sha512,
+    ];
+
+    /// Looks up the method identified by an `Algorithm` URI, if supported.
+    pub fn from_href(href: &str) -> Option<Self>
+    {
+        Self::ALL.iter()
+            .find(|m| unsafe { transform_href(m.to_method()) } == Some(href))
+            .copied()
+    }
+
+    /// Whether this is a plain digest method (e.g. SHA256), fit for `<dsig:DigestMethod/>` rather than
+    /// `<dsig:SignatureMethod/>`.
+    pub fn is_digest(&self) -> bool
+    {
+        unsafe { (*self.to_method()).usage & bindings::xmlSecTransformUsageDigestMethod != 0 }
+    }
+
     /// Returns the resource pointer for the corresponding digesting/signing resource
     pub fn to_method(&self) -> bindings::xmlSecTransformId
     {
@@ -103,3 +162,51 @@ impl XmlSecSignatureMethod
         }
     }
 }
+
+
+/// Kinds of key material, each backed by its own xmlsec key data klass.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XmlSecKeyKind
+{
+    Rsa,
+    Ec,
+    Dsa,
+    Hmac,
+    Aes,
+    Des3,
+}
+
+
+impl XmlSecKeyKind
+{
+    const ALL: [Self; 6] = [Self::Rsa, Self::Ec, Self::Dsa, Self::Hmac, Self::Aes, Self::Des3];
+
+    /// Looks up the kind of key held by key data of the klass `id`, if supported.
+    pub fn from_data_id(id: bindings::xmlSecKeyDataId) -> Option<Self>
+    {
+        Self::ALL.iter()
+            .find(|k| k.to_data_id() == id)
+            .copied()
+    }
+
+    /// Whether keys of this kind consist of a public and a private half.
+    pub fn is_asymmetric(&self) -> bool
+    {
+        matches!(self, Self::Rsa | Self::Ec | Self::Dsa)
+    }
+
+    /// Returns the resource pointer for the key data klass holding this kind of key
+    pub fn to_data_id(&self) -> bindings::xmlSecKeyDataId
+    {
+        match self
+        {
+            Self::Rsa  => unsafe { bindings::xmlSecOpenSSLKeyDataRsaGetKlass() },
+            Self::Ec   => unsafe { bindings::xmlSecOpenSSLKeyDataEcdsaGetKlass() },
+            Self::Dsa  => unsafe { bindings::xmlSecOpenSSLKeyDataDsaGetKlass() },
+            Self::Hmac => unsafe { bindings::xmlSecOpenSSLKeyDataHmacGetKlass() },
+            Self::Aes  => unsafe { bindings::xmlSecOpenSSLKeyDataAesGetKlass() },
+            Self::Des3 => unsafe { bindings::xmlSecOpenSSLKeyDataDesGetKlass() },
+        }
+    }
+}
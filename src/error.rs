@@ -1,6 +1,7 @@
 //!
 //! XmlSec High Level Error handling
 //!
+use std::ffi::CString;
 
 
 /// Wrapper project-wide Result typealias.
@@ -16,13 +17,28 @@ pub enum XmlSecError
 
     KeyNotLoaded,
     KeyLoadError,
+    KeyIoError(String),
+    KeyPasswordError(String),
+    KeyFormatError(String),
+    KeyGenerationError(String),
+    KeyExportError(String),
     CertLoadError,
+    CertificateError(String),
 
     RootNotFound,
     NodeNotFound,
 
     SigningError,
     VerifyError,
+
+    MalformedSignature(String),
+
+    InitError(String),
+    InvalidInput(String),
+    AllocationFailed(String),
+    TemplateError(String),
+    TransformError(String),
+    UnknownStatus(u32),
 }
 
 
@@ -37,11 +53,28 @@ impl std::fmt::Display for XmlSecError
             Self::KeyLoadError  => write!(fmt, "Failed to load key"),
             Self::CertLoadError => write!(fmt, "Failed to load certificate"),
 
+            Self::CertificateError(why) => write!(fmt, "Invalid certificate: {}", why),
+
+            Self::KeyIoError(detail)       => write!(fmt, "Failed to read key: {}", detail),
+            Self::KeyPasswordError(detail) => write!(fmt, "Wrong or missing password for key: {}", detail),
+            Self::KeyFormatError(detail)   => write!(fmt, "Key is malformed or not in the expected format: {}", detail),
+            Self::KeyGenerationError(why)  => write!(fmt, "Failed to generate key: {}", why),
+            Self::KeyExportError(why)      => write!(fmt, "Failed to export key: {}", why),
+
             Self::RootNotFound => write!(fmt, "Failed to find document root"),
             Self::NodeNotFound => write!(fmt, "Failed to find node"),
 
             Self::SigningError => write!(fmt, "An error has ocurred while attemting to sign document"),
             Self::VerifyError  => write!(fmt, "Verification process failed"),
+
+            Self::MalformedSignature(reason) => write!(fmt, "Malformed signature: {}", reason),
+
+            Self::InitError(reason)      => write!(fmt, "Failed to initialize xmlsec: {}", reason),
+            Self::InvalidInput(what)     => write!(fmt, "Invalid input: {}", what),
+            Self::AllocationFailed(what) => write!(fmt, "Failed to allocate {}", what),
+            Self::TemplateError(step)    => write!(fmt, "Failed to build signature template: {}", step),
+            Self::TransformError(step)   => write!(fmt, "Failed to run transforms: {}", step),
+            Self::UnknownStatus(code)    => write!(fmt, "Unknown xmlSecDSigStatus code {}", code),
         }
     }
 }
@@ -72,3 +105,10 @@ impl From<String> for XmlSecError
         Self::Str(other)
     }
 }
+
+
+/// Converts `value` for passing it on to xmlsec, with `what` naming it should it hold a NUL byte.
+pub(crate) fn cstring(value: &str, what: &str) -> XmlSecResult<CString>
+{
+    CString::new(value).map_err(|_| XmlSecError::InvalidInput(format!("{} holds an interior NUL byte", what)))
+}
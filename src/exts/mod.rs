@@ -3,4 +3,9 @@
 //!
 mod xml;
 
+#[cfg(feature = "openssl")]
+mod openssl;
+
 pub use xml::XmlSecDocumentExt;
+
+pub(crate) use xml::register_namespaces;
@@ -0,0 +1,184 @@
+//!
+//! XmlSec Extensions over the OpenSSL Wrapper
+//!
+use crate::bindings;
+
+use crate::XmlSecError;
+use crate::XmlSecKey;
+use crate::XmlSecKeysManager;
+use crate::XmlSecResult;
+
+use crate::keys::from_key_data;
+use crate::keysmngr::certificate_type;
+
+use ::openssl::pkey::HasPublic;
+use ::openssl::pkey::PKey;
+use ::openssl::pkey::PKeyRef;
+use ::openssl::pkey::Private;
+use ::openssl::pkey::Public;
+use ::openssl::x509::X509;
+use ::openssl::x509::X509Ref;
+use ::openssl::x509::store::X509StoreRef;
+
+use foreign_types::ForeignType;
+use foreign_types::ForeignTypeRef;
+
+
+impl XmlSecKey
+{
+    /// Create a key holding `pkey`, which is shared with it rather than copied.
+    pub fn from_pkey<T: HasPublic>(pkey: &PKeyRef<T>) -> XmlSecResult<Self>
+    {
+        crate::xmlsec::guarantee_xmlsec_init()?;
+
+        let ptr = pkey.as_ptr() as *mut bindings::EVP_PKEY;
+
+        // the reference taken here gets handed over to xmlsec
+        if unsafe { bindings::EVP_PKEY_up_ref(ptr) } != 1 {
+            return Err(XmlSecError::KeyFormatError("failed to reference OpenSSL key".into()));
+        }
+
+        let data = unsafe { bindings::xmlSecOpenSSLEvpKeyAdopt(ptr) };
+
+        if data.is_null()
+        {
+            unsafe { bindings::EVP_PKEY_free(ptr) };
+            return Err(XmlSecError::KeyFormatError("OpenSSL key type unsupported by xmlsec".into()));
+        }
+
+        from_key_data(data)
+    }
+
+    /// The private key as an OpenSSL key, sharing the key material rather than copying it.
+    pub fn to_pkey(&self) -> XmlSecResult<PKey<Private>>
+    {
+        let ptr = self.private_evp_key()?;
+
+        // the reference taken here gets handed over to the returned `PKey`
+        if unsafe { bindings::EVP_PKEY_up_ref(ptr) } != 1 {
+            return Err(XmlSecError::KeyExportError("failed to reference OpenSSL key".into()));
+        }
+
+        Ok(unsafe { PKey::from_ptr(ptr as *mut _) })
+    }
+
+    /// The public key as an OpenSSL key of its own, holding no private material.
+    pub fn to_public_pkey(&self) -> XmlSecResult<PKey<Public>>
+    {
+        PKey::public_key_from_der(&self.public_key_to_der()?)
+            .map_err(|e| XmlSecError::KeyExportError(e.to_string()))
+    }
+
+    /// Attach `cert` to the key, sharing it rather than copying it.
+    pub fn add_x509(&self, cert: &X509Ref) -> XmlSecResult<()>
+    {
+        let data = unsafe {
+            bindings::xmlSecKeyEnsureData(self.as_ptr(), bindings::xmlSecOpenSSLKeyDataX509GetKlass())
+        };
+
+        if data.is_null() {
+            return Err(XmlSecError::AllocationFailed("certificate data".into()));
+        }
+
+        let ptr = cert.as_ptr() as *mut bindings::X509;
+
+        // the reference taken here gets handed over to xmlsec
+        if unsafe { bindings::X509_up_ref(ptr) } != 1 {
+            return Err(XmlSecError::CertLoadError);
+        }
+
+        if unsafe { bindings::xmlSecOpenSSLKeyDataX509AdoptCert(data, ptr) } < 0
+        {
+            unsafe { bindings::X509_free(ptr) };
+            return Err(XmlSecError::CertLoadError);
+        }
+
+        Ok(())
+    }
+
+    /// Certificates attached to the key as OpenSSL certificates, in the order they were loaded.
+    pub fn x509_certificates(&self) -> XmlSecResult<Vec<X509>>
+    {
+        self.x509_certs()
+            .into_iter()
+            .map(|cert| {
+                // the reference taken here gets handed over to the returned `X509`
+                if unsafe { bindings::X509_up_ref(cert) } != 1 {
+                    return Err(XmlSecError::CertificateError("failed to reference OpenSSL certificate".into()));
+                }
+
+                Ok(unsafe { X509::from_ptr(cert as *mut _) })
+            })
+            .collect()
+    }
+}
+
+
+impl<T: HasPublic> TryFrom<&PKeyRef<T>> for XmlSecKey
+{
+    type Error = XmlSecError;
+
+    fn try_from(pkey: &PKeyRef<T>) -> XmlSecResult<Self>
+    {
+        Self::from_pkey(pkey)
+    }
+}
+
+
+impl TryFrom<&XmlSecKey> for PKey<Private>
+{
+    type Error = XmlSecError;
+
+    fn try_from(key: &XmlSecKey) -> XmlSecResult<Self>
+    {
+        key.to_pkey()
+    }
+}
+
+
+impl TryFrom<&XmlSecKey> for PKey<Public>
+{
+    type Error = XmlSecError;
+
+    fn try_from(key: &XmlSecKey) -> XmlSecResult<Self>
+    {
+        key.to_public_pkey()
+    }
+}
+
+
+impl XmlSecKeysManager
+{
+    /// Add `cert` to the certificates of the manager, sharing it rather than copying it. Only `trusted` certificates
+    /// anchor the verification of certificate chains.
+    pub fn add_x509(&mut self, cert: &X509Ref, trusted: bool) -> XmlSecResult<()>
+    {
+        let store = self.x509_store()?;
+
+        let ptr = cert.as_ptr() as *mut bindings::X509;
+
+        // the reference taken here gets handed over to xmlsec
+        if unsafe { bindings::X509_up_ref(ptr) } != 1 {
+            return Err(XmlSecError::CertLoadError);
+        }
+
+        if unsafe { bindings::xmlSecOpenSSLX509StoreAdoptCert(store, ptr, certificate_type(trusted)) } < 0
+        {
+            unsafe { bindings::X509_free(ptr) };
+            return Err(XmlSecError::CertLoadError);
+        }
+
+        Ok(())
+    }
+
+    /// Trust every certificate held by `store`, as if added with [`add_x509`][Self::add_x509].
+    pub fn add_x509_store(&mut self, store: &X509StoreRef) -> XmlSecResult<()>
+    {
+        for cert in store.objects().iter().filter_map(|o| o.x509())
+        {
+            self.add_x509(cert, true)?;
+        }
+
+        Ok(())
+    }
+}
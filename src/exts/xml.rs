@@ -1,14 +1,18 @@
 //!
 //! XmlSec Extensions over LibXML2 Wrapper
 //!
+use crate::XmlSecError;
 use crate::XmlSecResult;
 
 use crate::XmlDocument;
 use crate::XmlXPathContext;
 
+use crate::XmlSecCanonicalizationMethod;
+
+use crate::error::cstring;
+
 use libxml::bindings; // FIXME requires common bindings generation over libxml2
 
-use std::ffi::CString;
 use std::os::raw::c_uchar;
 use std::ptr::null_mut;
 
@@ -18,6 +22,16 @@ pub trait XmlSecDocumentExt
 {
     /// Specifies the name of an ID attribute on a set of nodes selected by an xpath search. See examples for usage.
     fn specify_idattr(&self, search: &str, idattr_name: &str, namespaces: Option<&[(&str, &str)]>) -> XmlSecResult<()>;
+
+    /// Canonicalizes exactly the nodes selected by an xpath search, following XPath node-set semantics: to take in
+    /// attributes or descendants, the expression must select them too (e.g. `//x | //x//node() | //x//@*`).
+    fn canonicalize_xpath(
+        &self,
+        search:             &str,
+        namespaces:         Option<&[(&str, &str)]>,
+        method:             XmlSecCanonicalizationMethod,
+        inclusive_prefixes: Option<&str>,
+    ) -> XmlSecResult<Vec<u8>>;
 }
 
 
@@ -26,28 +40,22 @@ impl XmlSecDocumentExt for XmlDocument
     fn specify_idattr(&self, search: &str, idattr_name: &str, namespaces: Option<&[(&str, &str)]>) -> XmlSecResult<()>
     {
         let xpath = XmlXPathContext::new(self)
-            .expect("Should not have failed to build xpath context XML document");
+            .map_err(|_| XmlSecError::AllocationFailed("XPath context over XML document".into()))?;
 
-        if let Some(nss) = namespaces
-        {
-            for (prefix, href) in nss {
-                xpath.register_namespace(prefix, href).ok();
-            }
+        if let Some(nss) = namespaces {
+            register_namespaces(&xpath, nss)?;
         }
 
         let result = match xpath.evaluate(search)
         {
             Ok(s)  => { s },
-            Err(_) => { return Err(format!("Should not have failed while xpath searching for '{}'", search).into()); }
+            Err(_) => { return Err(XmlSecError::InvalidInput(format!("XPath expression '{}'", search))); }
         };
 
         let subjnodes = result.get_nodes_as_vec();
 
-        if subjnodes.is_empty()
-        {
-            return Err(
-                format!("Could not find any nodes to specify ID attribute that go by the XPath: {}", search).into()
-            );
+        if subjnodes.is_empty() {
+            return Err(XmlSecError::NodeNotFound);
         }
 
         for node in &subjnodes
@@ -58,7 +66,7 @@ impl XmlSecDocumentExt for XmlDocument
                 let attrptr = attrnode.node_ptr() as *mut bindings::_xmlAttr;
 
                 let id     = attrnode.get_content();
-                let cid    = CString::new(id.clone()).unwrap();
+                let cid    = cstring(&id, "ID attribute value")?;
                 let cidptr = cid.as_ptr() as *mut c_uchar;
 
                 let existing = unsafe { bindings::xmlGetID(docptr, cidptr) };
@@ -66,11 +74,80 @@ impl XmlSecDocumentExt for XmlDocument
                 if existing.is_null() {
                     unsafe { bindings::xmlAddID(null_mut(), docptr, cidptr, attrptr) };
                 } else if existing != attrptr {
-                    return Err(format!("Error: duplicate ID attribute: {}", id).into());
+                    return Err(XmlSecError::InvalidInput(format!("duplicate ID attribute {}", id)));
                 }
             }
         }
 
         Ok(())
     }
+
+    fn canonicalize_xpath(
+        &self,
+        search:             &str,
+        namespaces:         Option<&[(&str, &str)]>,
+        method:             XmlSecCanonicalizationMethod,
+        inclusive_prefixes: Option<&str>,
+    ) -> XmlSecResult<Vec<u8>>
+    {
+        let xpath = XmlXPathContext::new(self)
+            .map_err(|_| XmlSecError::AllocationFailed("XPath context over XML document".into()))?;
+
+        if let Some(nss) = namespaces {
+            register_namespaces(&xpath, nss)?;
+        }
+
+        let result = match xpath.evaluate(search)
+        {
+            Ok(s)  => { s },
+            Err(_) => { return Err(XmlSecError::InvalidInput(format!("XPath expression '{}'", search))); }
+        };
+
+        let subjnodes = result.get_nodes_as_vec();
+
+        if subjnodes.is_empty() {
+            return Err(XmlSecError::NodeNotFound);
+        }
+
+        let set = unsafe { bindings::xmlXPathNodeSetCreate(null_mut()) };
+
+        if set.is_null() {
+            return Err(XmlSecError::AllocationFailed("node set to be canonicalized".into()));
+        }
+
+        for node in &subjnodes
+        {
+            if unsafe { bindings::xmlXPathNodeSetAdd(set, node.node_ptr()) } < 0
+            {
+                unsafe { bindings::xmlXPathFreeNodeSet(set) };
+                return Err(XmlSecError::TransformError(format!("adding nodes of '{}' to the node set", search)));
+            }
+        }
+
+        // same libxml2 structure, merely bound twice
+        crate::transforms::canonicalize_selection(self, set as *mut crate::bindings::xmlNodeSet, &method, inclusive_prefixes)
+    }
+}
+
+
+/// Binds each `(prefix, href)` pair within `xpath`, refusing those libxml2 does not accept (e.g. an empty prefix)
+/// rather than leaving the expression to fail on an unbound prefix later on.
+pub(crate) fn register_namespaces<P, H>(xpath: &XmlXPathContext, namespaces: &[(P, H)]) -> XmlSecResult<()>
+where
+    P: AsRef<str>,
+    H: AsRef<str>,
+{
+    for (prefix, href) in namespaces
+    {
+        let (prefix, href) = (prefix.as_ref(), href.as_ref());
+
+        // libxml panics on interior NUL bytes
+        cstring(prefix, "XPath namespace prefix")?;
+        cstring(href, "XPath namespace href")?;
+
+        xpath.register_namespace(prefix, href)
+            .map_err(|_| XmlSecError::InvalidInput(format!("XPath namespace binding '{}' to '{}'", prefix, href)))?;
+    }
+
+    Ok(())
 }
@@ -0,0 +1,368 @@
+//!
+//! Typed Inspection of Existing Signatures
+//!
+use crate::bindings;
+
+use crate::XmlNode;
+
+use crate::XmlSecCanonicalizationMethod;
+use crate::XmlSecSignatureMethod;
+
+use crate::XmlSecError;
+use crate::XmlSecResult;
+
+use std::slice;
+
+
+const DSIG_NS:     &str = "http://www.w3.org/2000/09/xmldsig#";
+const EXC_C14N_NS: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+
+const HREF_ENVELOPED: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+const HREF_BASE64:    &str = "http://www.w3.org/2000/09/xmldsig#base64";
+
+
+/// Contents of a `<dsig:Signature/>` node, as read without verifying anything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureInfo
+{
+    /// `Id` attribute of `<dsig:Signature/>`
+    pub id: Option<String>,
+
+    /// `<dsig:CanonicalizationMethod/>` of `<dsig:SignedInfo/>`
+    pub canonicalization: XmlSecCanonicalizationMethod,
+
+    /// `PrefixList` of the `<ec:InclusiveNamespaces/>` given to the canonicalization method
+    pub inclusive_namespaces: Option<String>,
+
+    /// `<dsig:SignatureMethod/>` of `<dsig:SignedInfo/>`
+    pub signature_method: XmlSecSignatureMethod,
+
+    /// `<dsig:Reference/>` nodes of `<dsig:SignedInfo/>`, in document order
+    pub references: Vec<ReferenceInfo>,
+
+    /// Decoded `<dsig:SignatureValue/>`
+    pub signature_value: Vec<u8>,
+
+    /// `<dsig:KeyInfo/>`, if any
+    pub key_info: Option<KeyInfoContent>,
+}
+
+
+/// Contents of a `<dsig:Reference/>` node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceInfo
+{
+    /// `URI` attribute, absent meaning the application knows what is referenced
+    pub uri: Option<String>,
+
+    /// `Id` attribute
+    pub id: Option<String>,
+
+    /// `Type` attribute
+    pub reference_type: Option<String>,
+
+    /// Transforms in the order they apply
+    pub transforms: Vec<ReferenceTransform>,
+
+    /// `<dsig:DigestMethod/>`
+    pub digest_method: XmlSecSignatureMethod,
+
+    /// Decoded `<dsig:DigestValue/>`
+    pub digest_value: Vec<u8>,
+}
+
+
+/// A single `<dsig:Transform/>` of a reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReferenceTransform
+{
+    /// Enveloped signature transform
+    Enveloped,
+
+    /// Canonicalization, along with the `PrefixList` of its `<ec:InclusiveNamespaces/>` if any
+    Canonicalization(XmlSecCanonicalizationMethod, Option<String>),
+
+    /// Base64 decoding transform
+    Base64,
+
+    /// Any other transform, by its `Algorithm` URI
+    Other(String),
+}
+
+
+/// Key related contents of a `<dsig:KeyInfo/>` node.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyInfoContent
+{
+    /// `Id` attribute
+    pub id: Option<String>,
+
+    /// Non-empty `<dsig:KeyName/>` values
+    pub key_names: Vec<String>,
+
+    /// DER encoded certificates of all `<dsig:X509Data/>/<dsig:X509Certificate/>` nodes
+    pub certificates: Vec<Vec<u8>>,
+}
+
+
+impl SignatureInfo
+{
+    /// Reads a `<dsig:Signature/>` node into its typed representation.
+    ///
+    /// # Errors
+    ///
+    /// If the node is not a `<dsig:Signature/>`, its children are missing or out of order, or it uses an algorithm
+    /// this crate has no method for.
+    pub fn parse(signature: &XmlNode) -> XmlSecResult<Self>
+    {
+        crate::xmlsec::guarantee_xmlsec_init()?;
+
+        expect_dsig(signature, "Signature")?;
+
+        let mut children = signature.get_child_elements().into_iter();
+
+        let signedinfo = next_dsig(&mut children, "SignedInfo")?;
+        let sigvalue   = next_dsig(&mut children, "SignatureValue")?;
+
+        let key_info = match children.next()
+        {
+            Some(node) if is_dsig(&node, "KeyInfo") => Some(KeyInfoContent::parse(&node)?),
+            Some(node) if is_dsig(&node, "Object")  => None,
+            Some(node)                              => return Err(unexpected(&node)),
+            None                                    => None,
+        };
+
+        if let Some(node) = children.find(|n| !is_dsig(n, "Object")) {
+            return Err(unexpected(&node));
+        }
+
+        let mut signedinfo_children = signedinfo.get_child_elements().into_iter();
+
+        let c14nnode = next_dsig(&mut signedinfo_children, "CanonicalizationMethod")?;
+        let signode  = next_dsig(&mut signedinfo_children, "SignatureMethod")?;
+
+        let canonicalization = XmlSecCanonicalizationMethod::from_href(&algorithm(&c14nnode)?)
+            .ok_or_else(|| unsupported(&c14nnode))?;
+
+        let signature_method = XmlSecSignatureMethod::from_href(&algorithm(&signode)?)
+            .ok_or_else(|| unsupported(&signode))?;
+
+        let references = signedinfo_children
+            .map(|node| {
+                expect_dsig(&node, "Reference")?;
+                ReferenceInfo::parse(&node)
+            })
+            .collect::<XmlSecResult<Vec<_>>>()?;
+
+        if references.is_empty() {
+            return Err(malformed("SignedInfo holds no Reference"));
+        }
+
+        Ok(Self {
+            id:                   signature.get_property("Id"),
+            inclusive_namespaces: inclusive_namespaces(&c14nnode),
+            signature_value:      decode_base64(&sigvalue)?,
+            canonicalization,
+            signature_method,
+            references,
+            key_info,
+        })
+    }
+}
+
+
+impl ReferenceInfo
+{
+    fn parse(reference: &XmlNode) -> XmlSecResult<Self>
+    {
+        let mut children = reference.get_child_elements().into_iter().peekable();
+
+        let mut transforms = Vec::new();
+
+        if let Some(node) = children.next_if(|node| is_dsig(node, "Transforms"))
+        {
+            for transform in node.get_child_elements()
+            {
+                expect_dsig(&transform, "Transform")?;
+                transforms.push(ReferenceTransform::parse(&transform)?);
+            }
+
+            if transforms.is_empty() {
+                return Err(malformed("Transforms holds no Transform"));
+            }
+        }
+
+        let digestnode  = next_dsig(&mut children, "DigestMethod")?;
+        let digestvalue = next_dsig(&mut children, "DigestValue")?;
+
+        if let Some(node) = children.next() {
+            return Err(unexpected(&node));
+        }
+
+        let digest_method = XmlSecSignatureMethod::from_href(&algorithm(&digestnode)?)
+            .ok_or_else(|| unsupported(&digestnode))?;
+
+        Ok(Self {
+            uri:            reference.get_property("URI"),
+            id:             reference.get_property("Id"),
+            reference_type: reference.get_property("Type"),
+            digest_value:   decode_base64(&digestvalue)?,
+            transforms,
+            digest_method,
+        })
+    }
+}
+
+
+impl ReferenceTransform
+{
+    fn parse(transform: &XmlNode) -> XmlSecResult<Self>
+    {
+        let href = algorithm(transform)?;
+
+        if let Some(c14n) = XmlSecCanonicalizationMethod::from_href(&href) {
+            return Ok(Self::Canonicalization(c14n, inclusive_namespaces(transform)));
+        }
+
+        Ok(match href.as_str()
+        {
+            HREF_ENVELOPED => Self::Enveloped,
+            HREF_BASE64    => Self::Base64,
+            _              => Self::Other(href.clone()),
+        })
+    }
+}
+
+
+impl KeyInfoContent
+{
+    fn parse(keyinfo: &XmlNode) -> XmlSecResult<Self>
+    {
+        let mut content = Self {id: keyinfo.get_property("Id"), ..Self::default()};
+
+        for child in keyinfo.get_child_elements()
+        {
+            if is_dsig(&child, "KeyName")
+            {
+                let name = child.get_content().trim().to_owned();
+
+                if !name.is_empty() {
+                    content.key_names.push(name);
+                }
+            }
+            else if is_dsig(&child, "X509Data")
+            {
+                for cert in child.get_child_elements().iter().filter(|n| is_dsig(n, "X509Certificate"))
+                {
+                    let der = decode_base64(cert)?;
+
+                    if !der.is_empty() {
+                        content.certificates.push(der);
+                    }
+                }
+            }
+        }
+
+        Ok(content)
+    }
+}
+
+
+pub(crate) fn is_dsig(node: &XmlNode, name: &str) -> bool
+{
+    node.get_name() == name && matches!(node.get_namespace(), Some(ns) if ns.get_href() == DSIG_NS)
+}
+
+
+fn expect_dsig(node: &XmlNode, name: &str) -> XmlSecResult<()>
+{
+    if !is_dsig(node, name) {
+        return Err(malformed(&format!("expected {} but found {}", name, node.get_name())));
+    }
+
+    Ok(())
+}
+
+
+fn next_dsig(nodes: &mut impl Iterator<Item = XmlNode>, name: &str) -> XmlSecResult<XmlNode>
+{
+    let node = nodes.next()
+        .ok_or_else(|| malformed(&format!("missing {}", name)))?;
+
+    expect_dsig(&node, name)?;
+
+    Ok(node)
+}
+
+
+fn algorithm(node: &XmlNode) -> XmlSecResult<String>
+{
+    node.get_property("Algorithm")
+        .ok_or_else(|| malformed(&format!("{} lacks an Algorithm", node.get_name())))
+}
+
+
+fn inclusive_namespaces(method: &XmlNode) -> Option<String>
+{
+    method.get_child_elements()
+        .into_iter()
+        .find(|n| n.get_name() == "InclusiveNamespaces"
+            && matches!(n.get_namespace(), Some(ns) if ns.get_href() == EXC_C14N_NS))
+        .and_then(|n| n.get_property("PrefixList"))
+}
+
+
+pub(crate) fn decode_base64(node: &XmlNode) -> XmlSecResult<Vec<u8>>
+{
+    // xmlsec refuses to decode empty content, as found in templates not yet signed
+    if node.get_content().trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let nodeptr = node.node_ptr() as *mut bindings::xmlNode;
+
+    unsafe {
+        let buffer = bindings::xmlSecBufferCreate(0);
+
+        if buffer.is_null() {
+            return Err(XmlSecError::AllocationFailed("base64 decoding buffer".into()));
+        }
+
+        let rc = bindings::xmlSecBufferBase64NodeContentRead(buffer, nodeptr);
+
+        let decoded = if rc < 0 || bindings::xmlSecBufferGetData(buffer).is_null() {
+            Vec::new()
+        } else {
+            slice::from_raw_parts(
+                bindings::xmlSecBufferGetData(buffer),
+                bindings::xmlSecBufferGetSize(buffer) as usize,
+            ).to_vec()
+        };
+
+        bindings::xmlSecBufferDestroy(buffer);
+
+        if rc < 0 {
+            return Err(malformed(&format!("{} is not valid base64", node.get_name())));
+        }
+
+        Ok(decoded)
+    }
+}
+
+
+fn malformed(reason: &str) -> XmlSecError
+{
+    XmlSecError::MalformedSignature(reason.to_owned())
+}
+
+
+fn unexpected(node: &XmlNode) -> XmlSecError
+{
+    malformed(&format!("unexpected {}", node.get_name()))
+}
+
+
+fn unsupported(node: &XmlNode) -> XmlSecError
+{
+    malformed(&format!("unsupported {} algorithm", node.get_name()))
+}
@@ -0,0 +1,631 @@
+//!
+//! Reading and Writing Keys in `<dsig:KeyInfo/>`
+//!
+use crate::bindings;
+
+use crate::XmlNode;
+use crate::XmlSecKey;
+use crate::XmlSecKeyFormat;
+use crate::XmlSecKeyKind;
+use crate::XmlSecKeysManager;
+
+use crate::XmlSecError;
+use crate::XmlSecResult;
+
+use crate::inspect::decode_base64;
+use crate::inspect::is_dsig;
+
+use crate::keys::certifies;
+
+use crate::stack;
+
+use std::os::raw::c_char;
+use std::os::raw::c_int;
+use std::os::raw::c_uchar;
+
+use std::ffi::CStr;
+use std::ffi::CString;
+
+use std::ptr::null;
+use std::ptr::null_mut;
+
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+
+const DSIG11_NS:   &str  = "http://www.w3.org/2009/xmldsig11#";
+const DSIG11_HREF: &[u8] = b"http://www.w3.org/2009/xmldsig11#\0";
+const XENC_NS:     &str  = "http://www.w3.org/2001/04/xmlenc#";
+
+
+/// How [`XmlSecKey::from_keyinfo_node`] reads a `<dsig:KeyInfo/>` node. By default certificates are taken at face
+/// value, so that the key of any signer can be shown or looked up before deciding whether to trust it.
+pub struct XmlSecKeyInfoOptions<'m>
+{
+    manager: Option<&'m XmlSecKeysManager>,
+    verify:  bool,
+    time:    Option<SystemTime>,
+}
+
+
+impl<'m> XmlSecKeyInfoOptions<'m>
+{
+    /// Options reading every child of `<dsig:KeyInfo/>` without verifying any certificate.
+    pub fn new() -> Self
+    {
+        Self {manager: None, verify: false, time: None}
+    }
+
+    /// Looks up keys named by `<dsig:KeyName/>` in `manager`, and verifies certificates against its trusted ones.
+    pub fn keys_manager(mut self, manager: &'m XmlSecKeysManager) -> Self
+    {
+        self.manager = Some(manager);
+        self
+    }
+
+    /// Takes the key of `<dsig:X509Data/>` only from a certificate chaining up to a trusted certificate of the keys
+    /// manager. Certificates are still attached to the key when failing to verify.
+    pub fn verify_certificates(mut self, verify: bool) -> Self
+    {
+        self.verify = verify;
+        self
+    }
+
+    /// Verifies certificates as of `time` instead of now.
+    pub fn verification_time(mut self, time: SystemTime) -> Self
+    {
+        self.time = Some(time);
+        self
+    }
+}
+
+
+/// What [`XmlSecKey::write_keyinfo_node`] puts into a `<dsig:KeyInfo/>` node. By default everything the key has:
+/// its name, its public key value and its certificates.
+pub struct XmlSecKeyInfoWriteOptions
+{
+    keyname:  bool,
+    keyvalue: bool,
+    x509data: bool,
+}
+
+
+impl XmlSecKeyInfoWriteOptions
+{
+    /// Options writing name, key value and certificates.
+    pub fn new() -> Self
+    {
+        Self {keyname: true, keyvalue: true, x509data: true}
+    }
+
+    /// Writes the name of the key into `<dsig:KeyName/>`
+    pub fn keyname(mut self, add: bool) -> Self
+    {
+        self.keyname = add;
+        self
+    }
+
+    /// Writes the public key into `<dsig:KeyValue/>`, e.g. the modulus and exponent of RSA keys, or the named curve
+    /// and point of EC keys within `<dsig11:ECKeyValue/>`
+    pub fn keyvalue(mut self, add: bool) -> Self
+    {
+        self.keyvalue = add;
+        self
+    }
+
+    /// Writes the certificates of the key into `<dsig:X509Data/>`, in the order they are attached to it
+    pub fn x509data(mut self, add: bool) -> Self
+    {
+        self.x509data = add;
+        self
+    }
+}
+
+
+impl XmlSecKey
+{
+    /// Reads the key described by a `<dsig:KeyInfo/>` node, without verifying any signature. Its value comes from
+    /// `<dsig:KeyValue/>`, the certificate of `<dsig:X509Data/>` holding the key, or `<dsig11:DEREncodedKeyValue/>`.
+    /// All certificates of `<dsig:X509Data/>` get attached to the key, and the first `<dsig:KeyName/>` becomes its
+    /// name. A key named only, with no value of its own nor found in the keys manager, has no [`kind`][Self::kind].
+    ///
+    /// # Errors
+    ///
+    /// If xmlsec fails to read any of the children, the node describes no key at all, or its certificates come along
+    /// with a key value that none of them holds.
+    pub fn from_keyinfo_node(node: &XmlNode, options: XmlSecKeyInfoOptions) -> XmlSecResult<Self>
+    {
+        crate::xmlsec::guarantee_xmlsec_init()?;
+
+        if !is_dsig(node, "KeyInfo") {
+            return Err(XmlSecError::KeyFormatError(format!("expected KeyInfo, found {}", node.get_name())));
+        }
+
+        // xmlsec requires a keys manager for reading certificates, an empty one does when none is given
+        let owned;
+
+        let manager = match options.manager
+        {
+            Some(manager) => manager,
+            None          => { owned = XmlSecKeysManager::new()?; &owned }
+        };
+
+        let ctx = unsafe { bindings::xmlSecKeyInfoCtxCreate(manager.as_ptr()) };
+
+        if ctx.is_null() {
+            return Err(XmlSecError::AllocationFailed("key info context".into()));
+        }
+
+        unsafe {
+            (*ctx).mode = bindings::xmlSecKeyInfoMode_xmlSecKeyInfoModeRead;
+
+            if !options.verify {
+                (*ctx).flags |= bindings::XMLSEC_KEYINFO_FLAGS_X509DATA_DONT_VERIFY_CERTS;
+            }
+
+            if let Some(time) = options.time {
+                (*ctx).certsVerificationTime = unix_time(time);
+            }
+        }
+
+        let key = unsafe { bindings::xmlSecKeyCreate() };
+
+        if key.is_null()
+        {
+            unsafe { bindings::xmlSecKeyInfoCtxDestroy(ctx) };
+            return Err(XmlSecError::AllocationFailed("key".into()));
+        }
+
+        let mut key = unsafe { Self::from_ptr(key) };
+
+        let children = node.get_child_elements();
+
+        // reading stops at the first key found, any certificates following it are read on their own afterwards.
+        // Reading on instead fails with two sources of key value, e.g. a key found by name and a key value.
+        let (rc, errors) = stack::capture(|| unsafe {
+            let rc = bindings::xmlSecKeyInfoNodeRead(node.node_ptr() as *mut bindings::xmlNode, key.as_ptr(), ctx);
+
+            let x509 = bindings::xmlSecOpenSSLKeyDataX509GetKlass();
+
+            if rc < 0 || !bindings::xmlSecKeyGetData(key.as_ptr(), x509).is_null() {
+                return rc;
+            }
+
+            children.iter()
+                .filter(|n| is_dsig(n, "X509Data"))
+                .map(|n| n.node_ptr() as *mut bindings::xmlNode)
+                .map(|n| bindings::xmlSecKeyDataXmlRead(x509, key.as_ptr(), n, ctx))
+                .find(|&rc| rc < 0)
+                .unwrap_or(rc)
+        });
+
+        unsafe { bindings::xmlSecKeyInfoCtxDestroy(ctx) };
+
+        if rc < 0 {
+            return Err(XmlSecError::KeyFormatError(errors.to_string()));
+        }
+
+        if has_zero_private_exponent(&key) {
+            key.adopt_value_of(&key.to_public()?)?;
+        }
+
+        if key.kind().is_none()
+        {
+            if let Some(der) = children.iter().find(|n| is_dsig11(n, "DEREncodedKeyValue")) {
+                key.adopt_value_of(&Self::from_memory(&decode_base64(der)?, XmlSecKeyFormat::Der, None)?)?;
+            }
+        }
+
+        check_certificates(&key)?;
+
+        // a keys manager not knowing the name leaves it unset
+        if key.get_name().is_none()
+        {
+            let name = children.iter()
+                .filter(|n| is_dsig(n, "KeyName"))
+                .map(|n| n.get_content().trim().to_owned())
+                .find(|n| !n.is_empty());
+
+            if let Some(name) = name {
+                key.set_name(&name)?;
+            }
+        }
+
+        if key.kind().is_none() && key.get_name().is_none() && key.x509_certs().is_empty() {
+            return Err(XmlSecError::KeyFormatError("KeyInfo describes no key".into()));
+        }
+
+        Ok(key)
+    }
+
+    /// Fills an existing `<dsig:KeyInfo/>` node from this key. Children already present act as template, e.g. a
+    /// `<dsig:KeyName/>` keeps its text, while those asked for by `options` get added when missing and applicable to
+    /// the key. Only public key material is ever written. xmlsec1 1.2 knows no `<dsig11:ECKeyValue/>`, which is
+    /// written here instead for EC keys.
+    pub fn write_keyinfo_node(&self, keyinfo: &XmlNode, options: XmlSecKeyInfoWriteOptions) -> XmlSecResult<()>
+    {
+        crate::xmlsec::guarantee_xmlsec_init()?;
+
+        if !is_dsig(keyinfo, "KeyInfo") {
+            return Err(XmlSecError::KeyExportError(format!("expected KeyInfo, found {}", keyinfo.get_name())));
+        }
+
+        let nodeptr = keyinfo.node_ptr() as *mut bindings::xmlNode;
+
+        let keyname  = options.keyname && self.get_name().is_some();
+        let keyvalue = options.keyvalue && self.kind().is_some_and(|k| k.is_asymmetric());
+        let x509data = options.x509data && !self.x509_certs().is_empty();
+
+        unsafe
+        {
+            if keyname && !has_dsig_child(nodeptr, &bindings::xmlSecNodeKeyName) {
+                added(bindings::xmlSecTmplKeyInfoAddKeyName(nodeptr, null()), "KeyName")?;
+            }
+
+            if keyvalue && !has_dsig_child(nodeptr, &bindings::xmlSecNodeKeyValue) {
+                added(bindings::xmlSecTmplKeyInfoAddKeyValue(nodeptr), "KeyValue")?;
+            }
+
+            if x509data && !has_dsig_child(nodeptr, &bindings::xmlSecNodeX509Data) {
+                added(bindings::xmlSecTmplKeyInfoAddX509Data(nodeptr), "X509Data")?;
+            }
+        }
+
+        let ctx = unsafe { bindings::xmlSecKeyInfoCtxCreate(null_mut()) };
+
+        if ctx.is_null() {
+            return Err(XmlSecError::AllocationFailed("key info context".into()));
+        }
+
+        // any other key type has xmlsec write private key material as well, such as the RSA private exponent
+        unsafe {
+            (*ctx).mode           = bindings::xmlSecKeyInfoMode_xmlSecKeyInfoModeWrite;
+            (*ctx).keyReq.keyType = bindings::xmlSecKeyDataTypePublic;
+        }
+
+        let ec = self.kind() == Some(XmlSecKeyKind::Ec);
+
+        // xmlsec fails on a KeyValue of EC keys, which it has to skip over instead
+        if ec
+        {
+            if let Err(e) = unsafe { skip_key_value(ctx) }
+            {
+                unsafe { bindings::xmlSecKeyInfoCtxDestroy(ctx) };
+                return Err(e);
+            }
+        }
+
+        let (rc, errors) = stack::capture(|| unsafe { bindings::xmlSecKeyInfoNodeWrite(nodeptr, self.as_ptr(), ctx) });
+
+        unsafe { bindings::xmlSecKeyInfoCtxDestroy(ctx) };
+
+        if rc < 0 {
+            return Err(XmlSecError::KeyExportError(errors.to_string()));
+        }
+
+        if ec
+        {
+            let empty = keyinfo.get_child_elements()
+                .into_iter()
+                .filter(|n| is_dsig(n, "KeyValue") && n.get_child_elements().is_empty());
+
+            for keyvalue in empty {
+                write_ec_key_value(keyvalue.node_ptr() as *mut bindings::xmlNode, self.evp_key()?)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fills the `<dsig:KeyInfo/>` child of `parent` from this key, like [`write_keyinfo_node`][write], creating the
+    /// child first if missing. Within `<dsig:Signature/>`, `<xenc:EncryptedData/>` and `<xenc:EncryptedKey/>` it is
+    /// put where the schema asks for it, anywhere else it becomes the last child. Returns the `<dsig:KeyInfo/>` node.
+    ///
+    /// [write]: struct.XmlSecKey.html#method.write_keyinfo_node
+    pub fn write_keyinfo_in(&self, parent: &XmlNode, options: XmlSecKeyInfoWriteOptions) -> XmlSecResult<XmlNode>
+    {
+        crate::xmlsec::guarantee_xmlsec_init()?;
+
+        let parentptr = parent.node_ptr() as *mut bindings::xmlNode;
+
+        let keyinfo = unsafe
+        {
+            if is_dsig(parent, "Signature") {
+                bindings::xmlSecTmplSignatureEnsureKeyInfo(parentptr, null())
+            } else if is_xenc(parent, "EncryptedData") || is_xenc(parent, "EncryptedKey") {
+                bindings::xmlSecTmplEncDataEnsureKeyInfo(parentptr, null())
+            } else {
+                match find_dsig_child(parentptr, &bindings::xmlSecNodeKeyInfo)
+                {
+                    found if !found.is_null() => found,
+
+                    _ => bindings::xmlSecAddChild(
+                        parentptr,
+                        &bindings::xmlSecNodeKeyInfo as *const c_uchar,
+                        &bindings::xmlSecDSigNs      as *const c_uchar,
+                    ),
+                }
+            }
+        };
+
+        if keyinfo.is_null() {
+            return Err(XmlSecError::AllocationFailed("KeyInfo node".into()));
+        }
+
+        let node = parent.get_child_elements()
+            .into_iter()
+            .find(|n| n.node_ptr() as *mut bindings::xmlNode == keyinfo)
+            .ok_or_else(|| XmlSecError::AllocationFailed("KeyInfo node".into()))?;
+
+        self.write_keyinfo_node(&node, options)?;
+
+        Ok(node)
+    }
+
+    /// Replaces the value of this key with a copy of the one of `other`.
+    fn adopt_value_of(&self, other: &Self) -> XmlSecResult<()>
+    {
+        let value = unsafe { bindings::xmlSecKeyDataDuplicate(bindings::xmlSecKeyGetValue(other.as_ptr())) };
+
+        if value.is_null() {
+            return Err(XmlSecError::AllocationFailed("key value".into()));
+        }
+
+        if unsafe { bindings::xmlSecKeySetValue(self.as_ptr(), value) } < 0
+        {
+            unsafe { bindings::xmlSecKeyDataDestroy(value) };
+            return Err(XmlSecError::AllocationFailed("key value".into()));
+        }
+
+        Ok(())
+    }
+}
+
+
+fn added(node: *mut bindings::xmlNode, what: &str) -> XmlSecResult<()>
+{
+    if node.is_null() {
+        return Err(XmlSecError::AllocationFailed(format!("{} node", what)));
+    }
+
+    Ok(())
+}
+
+
+/// Enables every kind of key data on `ctx` but the one of `<dsig:KeyValue/>`, which xmlsec then leaves alone.
+///
+/// # Safety
+///
+/// `ctx` must point to a valid key info context.
+unsafe fn skip_key_value(ctx: *mut bindings::xmlSecKeyInfoCtx) -> XmlSecResult<()>
+{
+    let ids   = bindings::xmlSecKeyDataIdsGet();
+    let value = bindings::xmlSecKeyDataValueGetKlass();
+
+    for pos in 0..bindings::xmlSecPtrListGetSize(ids)
+    {
+        let id = bindings::xmlSecPtrListGetItem(ids, pos);
+
+        if id as bindings::xmlSecKeyDataId != value && bindings::xmlSecPtrListAdd(&mut (*ctx).enabledKeyData, id) < 0 {
+            return Err(XmlSecError::AllocationFailed("enabled key data list".into()));
+        }
+    }
+
+    Ok(())
+}
+
+
+/// Fills an empty `<dsig:KeyValue/>` with the `<dsig11:ECKeyValue/>` of `pkey`, made of the OID of its named curve
+/// and its uncompressed public point.
+fn write_ec_key_value(keyvalue: *mut bindings::xmlNode, pkey: *mut bindings::EVP_PKEY) -> XmlSecResult<()>
+{
+    let uri   = format!("urn:oid:{}", ec_curve_oid(pkey)?);
+    let point = ec_public_point(pkey)?;
+
+    let ecvalue = add_dsig11_child(keyvalue, b"ECKeyValue\0")?;
+    let curve   = add_dsig11_child(ecvalue, b"NamedCurve\0")?;
+    let public  = add_dsig11_child(ecvalue, b"PublicKey\0")?;
+
+    let curi = CString::new(uri).map_err(|_| XmlSecError::KeyExportError("curve OID holds a NUL byte".into()))?;
+
+    if unsafe { bindings::xmlSetProp(curve, b"URI\0".as_ptr(), curi.as_ptr() as *const c_uchar) }.is_null() {
+        return Err(XmlSecError::AllocationFailed("NamedCurve URI attribute".into()));
+    }
+
+    let rc = unsafe
+    {
+        let buffer = bindings::xmlSecBufferCreate(point.len() as bindings::xmlSecSize);
+
+        if buffer.is_null() {
+            return Err(XmlSecError::AllocationFailed("EC point buffer".into()));
+        }
+
+        let mut rc = bindings::xmlSecBufferSetData(buffer, point.as_ptr(), point.len() as bindings::xmlSecSize);
+
+        if rc >= 0
+        {
+            let columns = bindings::xmlSecBase64GetDefaultLineSize();
+
+            rc = bindings::xmlSecBufferBase64NodeContentWrite(buffer, public, columns);
+        }
+
+        bindings::xmlSecBufferDestroy(buffer);
+
+        rc
+    };
+
+    if rc < 0 {
+        return Err(XmlSecError::KeyExportError("failed to base64 encode EC point".into()));
+    }
+
+    Ok(())
+}
+
+
+/// Dotted OID of the named curve of an EC key, e.g. `1.2.840.10045.3.1.7` for P-256.
+fn ec_curve_oid(pkey: *mut bindings::EVP_PKEY) -> XmlSecResult<String>
+{
+    let mut name   = [0 as c_char; 80];
+    let mut length = 0;
+
+    let rc = unsafe {
+        bindings::EVP_PKEY_get_utf8_string_param(
+            pkey,
+            b"group\0".as_ptr() as *const c_char,
+            name.as_mut_ptr(),
+            name.len(),
+            &mut length
+        )
+    };
+
+    if rc != 1 {
+        return Err(XmlSecError::KeyExportError("EC key has no named curve".into()));
+    }
+
+    let object = unsafe { bindings::OBJ_txt2obj(name.as_ptr(), 0) };
+
+    if object.is_null() {
+        return Err(XmlSecError::KeyExportError("EC curve has no OID".into()));
+    }
+
+    let mut oid = [0 as c_char; 80];
+
+    let length = unsafe { bindings::OBJ_obj2txt(oid.as_mut_ptr(), oid.len() as c_int, object, 1) };
+
+    unsafe { bindings::ASN1_OBJECT_free(object) };
+
+    if length <= 0 || length as usize >= oid.len() {
+        return Err(XmlSecError::KeyExportError("EC curve has no OID".into()));
+    }
+
+    Ok(unsafe { CStr::from_ptr(oid.as_ptr()) }.to_string_lossy().into_owned())
+}
+
+
+/// Public point of an EC key, in the uncompressed form XML Signature 1.1 asks for.
+fn ec_public_point(pkey: *mut bindings::EVP_PKEY) -> XmlSecResult<Vec<u8>>
+{
+    let mut point  = vec![0u8; 160];
+    let mut length = 0;
+
+    let rc = unsafe {
+        bindings::EVP_PKEY_get_octet_string_param(
+            pkey,
+            b"pub\0".as_ptr() as *const c_char,
+            point.as_mut_ptr(),
+            point.len(),
+            &mut length
+        )
+    };
+
+    // an uncompressed point leads with 0x04
+    if rc != 1 || point.first() != Some(&4) {
+        return Err(XmlSecError::KeyExportError("EC key has no uncompressed public point".into()));
+    }
+
+    point.truncate(length);
+
+    Ok(point)
+}
+
+
+/// xmlsec1 1.2 has no templating support for XML Signature 1.1 nodes, hence these get added by hand.
+fn add_dsig11_child(parent: *mut bindings::xmlNode, name: &[u8]) -> XmlSecResult<*mut bindings::xmlNode>
+{
+    let child = unsafe { bindings::xmlSecAddChild(
+        parent,
+        name.as_ptr(),
+        DSIG11_HREF.as_ptr(),
+    ) };
+
+    added(child, "XML Signature 1.1")?;
+
+    Ok(child)
+}
+
+
+fn is_xenc(node: &XmlNode, name: &str) -> bool
+{
+    node.get_name() == name && matches!(node.get_namespace(), Some(ns) if ns.get_href() == XENC_NS)
+}
+
+
+/// # Safety
+///
+/// `name` must be one of the null terminated xmlsec node name constants.
+unsafe fn find_dsig_child(node: *mut bindings::xmlNode, name: &[c_uchar; 0]) -> *mut bindings::xmlNode
+{
+    bindings::xmlSecFindChild(node, name as *const c_uchar, &bindings::xmlSecDSigNs as *const c_uchar)
+}
+
+
+/// # Safety
+///
+/// `name` must be one of the null terminated xmlsec node name constants.
+unsafe fn has_dsig_child(node: *mut bindings::xmlNode, name: &[c_uchar; 0]) -> bool
+{
+    !find_dsig_child(node, name).is_null()
+}
+
+
+fn is_dsig11(node: &XmlNode, name: &str) -> bool
+{
+    node.get_name() == name && matches!(node.get_namespace(), Some(ns) if ns.get_href() == DSIG11_NS)
+}
+
+
+/// Certificates are read with no regard for the key value, which must then be held by one of them, and by the one
+/// marked as key certificate if any. Otherwise the certificate of another party would pass for the one of the key.
+fn check_certificates(key: &XmlSecKey) -> XmlSecResult<()>
+{
+    let certs = key.x509_certs();
+
+    let pkey = match key.evp_key() {
+        Ok(pkey) if !certs.is_empty() => pkey,
+        _                             => return Ok(()),
+    };
+
+    let key_cert = key.key_cert();
+
+    if !certs.iter().any(|&cert| certifies(cert, pkey)) || (!key_cert.is_null() && !certifies(key_cert, pkey)) {
+        return Err(XmlSecError::KeyFormatError("X509Data holds no certificate of the key value".into()));
+    }
+
+    Ok(())
+}
+
+
+/// xmlsec reads `<dsig:RSAKeyValue/>` into a key pair with a zero private exponent, posing as a private key.
+fn has_zero_private_exponent(key: &XmlSecKey) -> bool
+{
+    if key.kind() != Some(XmlSecKeyKind::Rsa) || !key.is_private() {
+        return false;
+    }
+
+    let pkey = match key.evp_key() {
+        Ok(pkey) => pkey,
+        Err(_)   => return false,
+    };
+
+    let mut exponent = null_mut();
+
+    if unsafe { bindings::EVP_PKEY_get_bn_param(pkey, b"d\0".as_ptr() as *const c_char, &mut exponent) } != 1 {
+        return false;
+    }
+
+    let zero = unsafe { bindings::BN_is_zero(exponent) } == 1;
+
+    unsafe { bindings::BN_free(exponent) };
+
+    zero
+}
+
+
+fn unix_time(time: SystemTime) -> bindings::time_t
+{
+    match time.duration_since(UNIX_EPOCH)
+    {
+        Ok(after)   => after.as_secs() as bindings::time_t,
+        Err(before) => -(before.duration().as_secs() as bindings::time_t),
+    }
+}
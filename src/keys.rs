@@ -4,21 +4,39 @@
 use crate::bindings;
 
 use crate::XmlSecError;
+use crate::XmlSecKeyKind;
 use crate::XmlSecResult;
 
+use crate::error::cstring;
+
+use crate::stack;
+use crate::x509;
+use crate::x509::XmlSecCertificateInfo;
+use crate::x509::SHA256_LENGTH;
+use crate::stack::ErrorStack;
+
+use crate::password::password_callback;
+use crate::password::PasswordProvider;
+
 use std::ptr::null;
 use std::ptr::null_mut;
 
 use std::os::raw::c_char;
+use std::os::raw::c_int;
 use std::os::raw::c_uchar;
+use std::os::raw::c_uint;
+use std::os::raw::c_void;
 
 use std::ffi::CStr;
 use std::ffi::CString;
 
+use zeroize::Zeroizing;
+
 
 /// x509 key format.
 #[allow(missing_docs)]
 #[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum XmlSecKeyFormat
 {
     Unknown  = bindings::xmlSecKeyDataFormat_xmlSecKeyDataFormatUnknown,
@@ -33,6 +51,68 @@ pub enum XmlSecKeyFormat
 }
 
 
+// DER tags telling apart the ASN.1 structures keys and certificates come in
+const DER_INTEGER:      u8 = 0x02;
+const DER_BIT_STRING:   u8 = 0x03;
+const DER_OCTET_STRING: u8 = 0x04;
+const DER_SEQUENCE:     u8 = 0x30;
+
+
+impl XmlSecKeyFormat
+{
+    /// Guess the format of key or certificate material from its contents. PEM input is told apart by the labels of
+    /// its blocks, where any key takes precedence over certificates. DER input by the shape of its outermost ASN.1
+    /// structure, PKCS#8 keys being reported as [`Pkcs8Der`][Self::Pkcs8Der] whether encrypted or not, while
+    /// traditional and public keys load as [`Der`][Self::Der].
+    pub fn detect(buffer: &[u8]) -> Option<Self>
+    {
+        let text = String::from_utf8_lossy(buffer);
+
+        if text.contains("-----BEGIN ") {
+            detect_pem(&text)
+        } else {
+            detect_der(buffer)
+        }
+    }
+}
+
+
+/// Operations a key may be used for, see [`XmlSecKey::usage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XmlSecKeyUsage
+{
+    /// Creating signatures
+    pub sign: bool,
+
+    /// Verifying signatures
+    pub verify: bool,
+
+    /// Encrypting data
+    pub encrypt: bool,
+
+    /// Decrypting data
+    pub decrypt: bool,
+
+    /// Wrapping or transporting other keys
+    pub key_exchange: bool,
+}
+
+
+/// Material of a key to export, see [`XmlSecKey::to_pem`] and [`XmlSecKey::to_der`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XmlSecKeyDataType
+{
+    /// Private key, as PKCS#8
+    Private,
+
+    /// Public key, as SubjectPublicKeyInfo
+    Public,
+
+    /// Attached certificate at the given position, in the order certificates were loaded
+    Certificate(usize),
+}
+
+
 /// Key with which we sign/verify signatures or encrypt data. Used by [`XmlSecSignatureContext`][sigctx].
 ///
 /// [sigctx]: struct.XmlSecSignatureContext.html
@@ -48,26 +128,26 @@ impl XmlSecKey
     {
         // TODO deprecate internals for Rust read-from-file and then loading with `from_memory`
 
-        crate::xmlsec::guarantee_xmlsec_init();
+        crate::xmlsec::guarantee_xmlsec_init()?;
 
-        // TODO proper sanitization/error handling of input
-        let cpath   = CString::new(path).unwrap();
-        let cpasswd = password.map(|p| CString::new(p).unwrap());
+        let cpath   = cstring(path, "key path")?;
+        let cpasswd = password.map(|p| cstring(p, "key password")).transpose()?;
 
-        let cpasswd_ptr = cpasswd.map(|cstr| cstr.as_ptr())
+        let cpasswd_ptr = cpasswd.as_ref()
+            .map(|cstr| cstr.as_ptr())
             .unwrap_or(null());
 
         // Load key from file
-        let key = unsafe { bindings::xmlSecOpenSSLAppKeyLoad(
+        let (key, errors) = stack::capture(|| unsafe { bindings::xmlSecOpenSSLAppKeyLoad(
             cpath.as_ptr(),
             format as u32,
             cpasswd_ptr,
             null_mut(),
             null_mut()
-        ) };
+        ) });
 
         if key.is_null() {
-            return Err(XmlSecError::KeyLoadError);
+            return Err(load_error(errors));
         }
 
         Ok(Self(key))
@@ -76,35 +156,450 @@ impl XmlSecKey
     /// Load key from buffer in memory, specifying format and optionally the password required to decrypt/unlock.
     pub fn from_memory(buffer: &[u8], format: XmlSecKeyFormat, password: Option<&str>) -> XmlSecResult<Self>
     {
-        crate::xmlsec::guarantee_xmlsec_init();
+        crate::xmlsec::guarantee_xmlsec_init()?;
 
-        // TODO proper sanitization/error handling of input
-        let cpasswd = password.map(|p| CString::new(p).unwrap());
+        let cpasswd = password.map(|p| cstring(p, "key password")).transpose()?;
 
-        let cpasswd_ptr = cpasswd.map(|cstr| cstr.as_ptr())
+        let cpasswd_ptr = cpasswd.as_ref()
+            .map(|cstr| cstr.as_ptr())
             .unwrap_or(null());
 
         // Load key from buffer
-        let key = unsafe { bindings::xmlSecOpenSSLAppKeyLoadMemory(
+        let (key, errors) = stack::capture(|| unsafe { bindings::xmlSecOpenSSLAppKeyLoadMemory(
             buffer.as_ptr(),
             buffer.len() as u32,
-            format as u32,
+            loader_format(buffer, format) as u32,
             cpasswd_ptr,
             null_mut(),
             null_mut()
-        ) };
+        ) });
+
+        if key.is_null() {
+            return Err(load_error(errors));
+        }
+
+        Ok(Self(key))
+    }
+
+    /// Load key from file like [`from_file`][Self::from_file], but ask `password` for the passphrase only once the
+    /// key turns out to be encrypted. Returning `None` aborts the load. The password is zeroized after use.
+    pub fn from_file_with_provider<F>(path: &str, format: XmlSecKeyFormat, mut password: F) -> XmlSecResult<Self>
+    where
+        F: FnMut() -> Option<String>
+    {
+        crate::xmlsec::guarantee_xmlsec_init()?;
+
+        let cpath = cstring(path, "key path")?;
+
+        let mut provider = PasswordProvider::new(&mut password);
+        let cpasswd      = eager_password(&format, &mut provider)?;
+
+        let cpasswd_ptr = cpasswd.as_ref()
+            .map(|cstr| cstr.as_ptr())
+            .unwrap_or(null());
+
+        let (key, errors) = stack::capture(|| unsafe { bindings::xmlSecOpenSSLAppKeyLoad(
+            cpath.as_ptr(),
+            format as u32,
+            cpasswd_ptr,
+            password_callback as *mut c_void,
+            provider.as_userdata()
+        ) });
+
+        if key.is_null() {
+            return Err(load_error(errors));
+        }
+
+        Ok(Self(key))
+    }
+
+    /// Load key from buffer in memory like [`from_memory`][Self::from_memory], but ask `password` for the passphrase
+    /// only once the key turns out to be encrypted. Returning `None` aborts the load. The password is zeroized after
+    /// use.
+    pub fn from_memory_with_provider<F>(buffer: &[u8], format: XmlSecKeyFormat, mut password: F) -> XmlSecResult<Self>
+    where
+        F: FnMut() -> Option<String>
+    {
+        crate::xmlsec::guarantee_xmlsec_init()?;
+
+        let mut provider = PasswordProvider::new(&mut password);
+        let cpasswd      = eager_password(&format, &mut provider)?;
+
+        let cpasswd_ptr = cpasswd.as_ref()
+            .map(|cstr| cstr.as_ptr())
+            .unwrap_or(null());
+
+        let (key, errors) = stack::capture(|| unsafe { bindings::xmlSecOpenSSLAppKeyLoadMemory(
+            buffer.as_ptr(),
+            buffer.len() as u32,
+            loader_format(buffer, format) as u32,
+            cpasswd_ptr,
+            password_callback as *mut c_void,
+            provider.as_userdata()
+        ) });
+
+        if key.is_null() {
+            return Err(load_error(errors));
+        }
+
+        Ok(Self(key))
+    }
+
+    /// Load key or certificate from buffer in memory, detecting its format with [`XmlSecKeyFormat::detect`].
+    /// Returns the key along with the format it was loaded as. `password` is used for encrypted input only, which
+    /// fails to load without one instead of prompting for it.
+    pub fn from_memory_auto(buffer: &[u8], password: Option<&str>) -> XmlSecResult<(Self, XmlSecKeyFormat)>
+    {
+        let format = XmlSecKeyFormat::detect(buffer)
+            .ok_or_else(|| XmlSecError::KeyFormatError("unrecognized key or certificate format".into()))?;
+
+        let key = Self::from_memory_with_provider(buffer, format, || password.map(str::to_owned))?;
+
+        Ok((key, format))
+    }
+
+    /// Generate a fresh key of `kind`. `size` is its length in bits: 128, 192 or 256 for AES, 192 for 3DES and any
+    /// whole number of bytes for RSA and HMAC. EC keys get generated on the NIST curve of `size` bits, which is one
+    /// of 256, 384 or 521. DSA keys cannot be generated.
+    pub fn generate(kind: XmlSecKeyKind, size: u32) -> XmlSecResult<Self>
+    {
+        crate::xmlsec::guarantee_xmlsec_init()?;
+
+        if kind == XmlSecKeyKind::Dsa {
+            return Err(XmlSecError::KeyGenerationError("DSA keys cannot be generated".into()));
+        }
+
+        if !valid_size(kind, size) {
+            return Err(XmlSecError::KeyGenerationError(format!("{} bits are not supported for {:?} keys", size, kind)));
+        }
+
+        // xmlsec provides no generator for EC key data
+        if kind == XmlSecKeyKind::Ec {
+            return generate_ec(size);
+        }
+
+        let (key, errors) = stack::capture(|| unsafe {
+            bindings::xmlSecKeyGenerate(kind.to_data_id(), size, bindings::xmlSecKeyDataTypeAny)
+        });
+
+        if key.is_null() {
+            return Err(XmlSecError::KeyGenerationError(errors.to_string()));
+        }
+
+        Ok(Self(key))
+    }
+
+    /// Create an HMAC key from its shared `secret`.
+    pub fn from_hmac_secret(secret: &[u8]) -> XmlSecResult<Self>
+    {
+        Self::from_symmetric_bytes(XmlSecKeyKind::Hmac, secret)
+    }
+
+    /// Create a symmetric key of `kind` from its raw `bytes`, which need to be of a length fitting `kind` as listed
+    /// for [`generate`][Self::generate]. Fails for asymmetric kinds, whose material does not come as raw bytes.
+    pub fn from_symmetric_bytes(kind: XmlSecKeyKind, bytes: &[u8]) -> XmlSecResult<Self>
+    {
+        crate::xmlsec::guarantee_xmlsec_init()?;
+
+        check_symmetric(kind, bytes.len())?;
+
+        let (key, errors) = stack::capture(|| unsafe {
+            bindings::xmlSecKeyReadMemory(kind.to_data_id(), bytes.as_ptr(), bytes.len() as u32)
+        });
+
+        if key.is_null() {
+            return Err(XmlSecError::KeyFormatError(errors.to_string()));
+        }
+
+        Ok(Self(key))
+    }
+
+    /// Create a symmetric key of `kind` from a file holding nothing but its raw bytes. See
+    /// [`from_symmetric_bytes`][Self::from_symmetric_bytes].
+    pub fn from_symmetric_file(kind: XmlSecKeyKind, path: &str) -> XmlSecResult<Self>
+    {
+        crate::xmlsec::guarantee_xmlsec_init()?;
+
+        let cpath = cstring(path, "key path")?;
+
+        // checked up front, since xmlsec does not tell I/O failures apart
+        let size = std::fs::metadata(path)
+            .map_err(|e| XmlSecError::KeyIoError(format!("{}: {}", path, e)))?
+            .len();
+
+        check_symmetric(kind, usize::try_from(size).unwrap_or(usize::MAX))?;
+
+        let (key, errors) = stack::capture(|| unsafe {
+            bindings::xmlSecKeyReadBinaryFile(kind.to_data_id(), cpath.as_ptr())
+        });
 
         if key.is_null() {
-            return Err(XmlSecError::KeyLoadError);
+            return Err(load_error(errors));
         }
 
         Ok(Self(key))
     }
 
+    /// Export the selected material of the key as PEM. Copies of private material get zeroized when dropped, as does
+    /// the buffer they were encoded into.
+    pub fn to_pem(&self, data: XmlSecKeyDataType) -> XmlSecResult<Zeroizing<String>>
+    {
+        match data
+        {
+            XmlSecKeyDataType::Private            => self.private_key_to_pem(),
+            XmlSecKeyDataType::Public             => self.public_key_to_pem().map(Zeroizing::new),
+            XmlSecKeyDataType::Certificate(index) => {
+                let cert = self.x509_cert_at(index)?;
+
+                into_pem(write_bio(false, |bio| unsafe { bindings::PEM_write_bio_X509(bio, cert) })?)
+            }
+        }
+    }
+
+    /// Export the selected material of the key as DER. Copies of private material get zeroized when dropped, as does
+    /// the buffer they were encoded into.
+    pub fn to_der(&self, data: XmlSecKeyDataType) -> XmlSecResult<Zeroizing<Vec<u8>>>
+    {
+        match data
+        {
+            XmlSecKeyDataType::Private            => self.private_key_to_der(),
+            XmlSecKeyDataType::Public             => self.public_key_to_der().map(Zeroizing::new),
+            XmlSecKeyDataType::Certificate(index) => {
+                let cert = self.x509_cert_at(index)?;
+
+                write_bio(false, |bio| unsafe { bindings::i2d_X509_bio(bio, cert) })
+            }
+        }
+    }
+
+    /// Export the private key as PKCS#8 PEM. The returned copy gets zeroized when dropped, as does the buffer it was
+    /// encoded into.
+    pub fn private_key_to_pem(&self) -> XmlSecResult<Zeroizing<String>>
+    {
+        let pkey = self.private_evp_key()?;
+
+        let pem = write_bio(true, |bio| unsafe {
+            bindings::PEM_write_bio_PrivateKey(bio, pkey, null(), null(), 0, None, null_mut())
+        })?;
+
+        into_pem(pem)
+    }
+
+    /// Export the private key as PKCS#8 DER. The returned copy gets zeroized when dropped, as does the buffer it was
+    /// encoded into.
+    pub fn private_key_to_der(&self) -> XmlSecResult<Zeroizing<Vec<u8>>>
+    {
+        let pkey = self.private_evp_key()?;
+
+        write_bio(true, |bio| unsafe {
+            bindings::i2d_PKCS8PrivateKey_bio(bio, pkey, null(), null(), 0, None, null_mut())
+        })
+    }
+
+    /// Export the public key as SubjectPublicKeyInfo PEM.
+    pub fn public_key_to_pem(&self) -> XmlSecResult<String>
+    {
+        let pkey = self.evp_key()?;
+
+        let pem = write_bio(false, |bio| unsafe { bindings::PEM_write_bio_PUBKEY(bio, pkey) })?;
+
+        into_pem(pem).map(|p| p.to_string())
+    }
+
+    /// Export the public key as SubjectPublicKeyInfo DER.
+    pub fn public_key_to_der(&self) -> XmlSecResult<Vec<u8>>
+    {
+        let pkey = self.evp_key()?;
+
+        write_bio(false, |bio| unsafe { bindings::i2d_PUBKEY_bio(bio, pkey) })
+            .map(|mut der| std::mem::take(&mut *der))
+    }
+
+    /// Export each certificate attached to the key as PEM, in the order they were loaded.
+    pub fn certificates_to_pem(&self) -> XmlSecResult<Vec<String>>
+    {
+        self.x509_certs().into_iter()
+            .map(|cert| write_bio(false, |bio| unsafe { bindings::PEM_write_bio_X509(bio, cert) }))
+            .map(|pem| into_pem(pem?).map(|p| p.to_string()))
+            .collect()
+    }
+
+    /// Export each certificate attached to the key as DER, in the order they were loaded.
+    pub fn certificates_to_der(&self) -> XmlSecResult<Vec<Vec<u8>>>
+    {
+        self.x509_certs().into_iter()
+            .map(|cert| write_bio(false, |bio| unsafe { bindings::i2d_X509_bio(bio, cert) }))
+            .map(|der| der.map(|mut d| std::mem::take(&mut *d)))
+            .collect()
+    }
+
+    /// Kind of the key material held, if any and known.
+    pub fn kind(&self) -> Option<XmlSecKeyKind>
+    {
+        let value = unsafe { bindings::xmlSecKeyGetValue(self.0) };
+
+        if value.is_null() {
+            return None;
+        }
+
+        XmlSecKeyKind::from_data_id(unsafe { (*value).id })
+    }
+
+    /// Size of the key in bits: the modulus for RSA and DSA, the curve order for EC and the secret for symmetric keys.
+    /// Zero if no key material is held.
+    pub fn size(&self) -> u32
+    {
+        if let Ok(pkey) = self.evp_key() {
+            return unsafe { bindings::EVP_PKEY_get_bits(pkey) }.max(0) as u32;
+        }
+
+        let value = unsafe { bindings::xmlSecKeyGetValue(self.0) };
+
+        if value.is_null() {
+            return 0;
+        }
+
+        unsafe { bindings::xmlSecKeyDataGetSize(value) }
+    }
+
+    /// Whether the key holds private material, that is the private half of a key pair or a symmetric secret.
+    pub fn is_private(&self) -> bool
+    {
+        let kind = unsafe { bindings::xmlSecKeyGetType(self.0) };
+
+        kind & (bindings::xmlSecKeyDataTypePrivate | bindings::xmlSecKeyDataTypeSymmetric) != 0
+    }
+
+    /// Operations the key may be used for, as restricted by xmlsec and by the material it holds. A public key can
+    /// neither sign nor decrypt, and no EC, DSA or HMAC key takes part in encryption.
+    pub fn usage(&self) -> XmlSecKeyUsage
+    {
+        let kind    = self.kind();
+        let allowed = unsafe { (*self.0).usage };
+        let private = self.is_private();
+
+        let signing = matches!(
+            kind,
+            Some(XmlSecKeyKind::Rsa | XmlSecKeyKind::Ec | XmlSecKeyKind::Dsa | XmlSecKeyKind::Hmac)
+        );
+
+        let encryption = matches!(kind, Some(XmlSecKeyKind::Rsa | XmlSecKeyKind::Aes | XmlSecKeyKind::Des3));
+
+        let has = |flag: u32| allowed & flag != 0;
+
+        XmlSecKeyUsage {
+            sign:         signing && private && has(bindings::xmlSecKeyUsageSign),
+            verify:       signing && has(bindings::xmlSecKeyUsageVerify),
+            encrypt:      encryption && has(bindings::xmlSecKeyUsageEncrypt),
+            decrypt:      encryption && private && has(bindings::xmlSecKeyUsageDecrypt),
+            key_exchange: encryption && has(bindings::xmlSecKeyUsageKeyExchange),
+        }
+    }
+
+    /// Details of each certificate attached to the key, in the order they were loaded.
+    pub fn certificates(&self) -> XmlSecResult<Vec<XmlSecCertificateInfo>>
+    {
+        let own = self.key_cert();
+
+        self.x509_certs().into_iter()
+            .map(|cert| {
+                let mut info = x509::describe(cert)?;
+                info.key_certificate = !own.is_null() && unsafe { bindings::X509_cmp(own, cert) } == 0;
+                Ok(info)
+            })
+            .collect()
+    }
+
+    /// SHA-256 digest of the DER encoded SubjectPublicKeyInfo of an asymmetric key, alike for a key pair and its
+    /// public half. Suits pinning a signer independently of the certificate it comes with.
+    pub fn public_key_fingerprint(&self) -> XmlSecResult<[u8; SHA256_LENGTH]>
+    {
+        sha256(&self.public_key_to_der()?)
+    }
+
+    /// SHA-256 digest of the DER encoded certificate of the key, also known as its X509 thumbprint. That is the
+    /// certificate marked as key certificate, or else the first attached one holding the public key of this key.
+    pub fn x509_thumbprint(&self) -> XmlSecResult<Option<[u8; SHA256_LENGTH]>>
+    {
+        let mut cert = self.key_cert();
+
+        if cert.is_null()
+        {
+            let pkey = match self.evp_key() {
+                Ok(pkey) => pkey,
+                Err(_)   => return Ok(None),
+            };
+
+            cert = self.x509_certs().into_iter()
+                .find(|&cert| certifies(cert, pkey))
+                .unwrap_or(null_mut());
+        }
+
+        if cert.is_null() {
+            return Ok(None);
+        }
+
+        x509::fingerprint(cert).map(Some)
+    }
+
+    /// Derive a key holding only the public half of this key pair, along with its name, usage and certificates. It
+    /// verifies signatures and encrypts towards this key, but cannot sign nor decrypt.
+    pub fn to_public(&self) -> XmlSecResult<Self>
+    {
+        let der = self.public_key_to_der()?;
+
+        let public = Self::from_memory(&der, XmlSecKeyFormat::Der, None)?;
+
+        unsafe { (*public.0).usage = (*self.0).usage };
+
+        let name = unsafe { bindings::xmlSecKeyGetName(self.0) };
+
+        if !name.is_null() && unsafe { bindings::xmlSecKeySetName(public.0, name) } < 0 {
+            return Err(XmlSecError::AllocationFailed("key name".into()));
+        }
+
+        let x509 = unsafe { bindings::xmlSecKeyGetData(self.0, bindings::xmlSecOpenSSLKeyDataX509GetKlass()) };
+
+        if x509.is_null() {
+            return Ok(public);
+        }
+
+        let copy = unsafe { bindings::xmlSecKeyDataDuplicate(x509) };
+
+        if copy.is_null() {
+            return Err(XmlSecError::AllocationFailed("certificate data".into()));
+        }
+
+        if unsafe { bindings::xmlSecKeyAdoptData(public.0, copy) } < 0
+        {
+            unsafe { bindings::xmlSecKeyDataDestroy(copy) };
+            return Err(XmlSecError::AllocationFailed("certificate data".into()));
+        }
+
+        Ok(public)
+    }
+
+    /// Duplicate the key along with its name, usage and certificates. Unlike [`clone`][clone], allocation failures
+    /// are reported rather than panicking.
+    ///
+    /// [clone]: #method.clone
+    pub fn try_clone(&self) -> XmlSecResult<Self>
+    {
+        let new = unsafe { bindings::xmlSecKeyDuplicate(self.0) };
+
+        if new.is_null() {
+            return Err(XmlSecError::AllocationFailed("key duplicate".into()));
+        }
+
+        Ok(Self(new))
+    }
+
     /// Load certificate into key by specifying path and ints format.
     pub fn load_cert_from_file(&self, path: &str, format: XmlSecKeyFormat) -> XmlSecResult<()>
     {
-        let cpath = CString::new(path).unwrap();
+        crate::xmlsec::guarantee_xmlsec_init()?;
+
+        let cpath = cstring(path, "certificate path")?;
 
         let rc = unsafe { bindings::xmlSecOpenSSLAppKeyCertLoad(self.0, cpath.as_ptr(), format as u32) };
 
@@ -134,10 +629,192 @@ impl XmlSecKey
         Ok(())
     }
 
+    /// Load a whole certificate chain into key by specifying path and its format. See
+    /// [`load_cert_chain_from_memory`][chain] for details.
+    ///
+    /// [chain]: struct.XmlSecKey.html#method.load_cert_chain_from_memory
+    pub fn load_cert_chain_from_file(&self, path: &str, format: XmlSecKeyFormat, password: Option<&str>)
+        -> XmlSecResult<usize>
+    {
+        let buff = std::fs::read(path)
+            .map_err(|e| XmlSecError::KeyIoError(format!("{}: {}", path, e)))?;
+
+        self.load_cert_chain_from_memory(&buff, format, password)
+    }
+
+    /// Load a whole certificate chain into key by specifying buffer to its contents, either a bundle of PEM
+    /// certificates or a PKCS#12 file decrypted with `password`. Every certificate is added to the X509 data of the
+    /// key, with the one certifying the key itself moved to the front and marked as its key certificate. Certificates
+    /// the key already holds are skipped, and on failure the key keeps the certificates it had. Returns the number of
+    /// certificates added.
+    pub fn load_cert_chain_from_memory(&self, buff: &[u8], format: XmlSecKeyFormat, password: Option<&str>)
+        -> XmlSecResult<usize>
+    {
+        let certs = match format
+        {
+            XmlSecKeyFormat::Pem | XmlSecKeyFormat::CertPem => read_pem_certs(buff)?,
+
+            XmlSecKeyFormat::Pkcs12 => {
+                let bundle = Self::from_memory(buff, format, password)?;
+
+                let mut certs = Vec::new();
+
+                for cert in bundle.x509_certs()
+                {
+                    // references taken here get handed over to the chain
+                    if unsafe { bindings::X509_up_ref(cert) } != 1
+                    {
+                        free_certs(certs);
+                        return Err(XmlSecError::CertLoadError);
+                    }
+
+                    certs.push(cert);
+                }
+
+                certs
+            }
+
+            _ => return Err(XmlSecError::CertLoadError),
+        };
+
+        if certs.is_empty() {
+            return Err(XmlSecError::CertLoadError);
+        }
+
+        self.adopt_chain(certs)
+    }
+
+    /// Adds the owned references of `certs` to the X509 data of the key, putting first and marking the one whose
+    /// public key matches the one of this key. Certificates the key already holds are skipped. The chain is gathered
+    /// on a copy of the X509 data which only replaces the original once every certificate made it in, so that a
+    /// failure leaves the key as it was.
+    fn adopt_chain(&self, certs: Vec<*mut bindings::X509>) -> XmlSecResult<usize>
+    {
+        let known = self.x509_certs()
+            .into_iter()
+            .chain(Some(self.key_cert()).filter(|cert| !cert.is_null()))
+            .map(x509::fingerprint)
+            .collect::<XmlSecResult<Vec<_>>>();
+
+        let mut known = match known
+        {
+            Ok(known) => known,
+            Err(e)    => {
+                free_certs(certs);
+                return Err(e);
+            }
+        };
+
+        let mut fresh   = Vec::new();
+        let mut failure = None;
+
+        for cert in certs
+        {
+            match x509::fingerprint(cert)
+            {
+                Ok(digest) if failure.is_none() && !known.contains(&digest) => {
+                    known.push(digest);
+                    fresh.push(cert);
+                }
+
+                Ok(_) => unsafe { bindings::X509_free(cert) },
+
+                Err(e) => {
+                    unsafe { bindings::X509_free(cert) };
+                    failure.get_or_insert(e);
+                }
+            }
+        }
+
+        if let Some(e) = failure
+        {
+            free_certs(fresh);
+            return Err(e);
+        }
+
+        if fresh.is_empty() {
+            return Ok(0);
+        }
+
+        let klass   = unsafe { bindings::xmlSecOpenSSLKeyDataX509GetKlass() };
+        let current = unsafe { bindings::xmlSecKeyGetData(self.0, klass) };
+
+        let data = if current.is_null() {
+            unsafe { bindings::xmlSecKeyDataCreate(klass) }
+        } else {
+            unsafe { bindings::xmlSecKeyDataDuplicate(current) }
+        };
+
+        if data.is_null()
+        {
+            free_certs(fresh);
+            return Err(XmlSecError::AllocationFailed("certificate data".into()));
+        }
+
+        let count = fresh.len();
+
+        if let Err(e) = self.fill_chain(data, fresh)
+        {
+            unsafe { bindings::xmlSecKeyDataDestroy(data) };
+            return Err(e);
+        }
+
+        // replaces the X509 data previously held by the key, if any
+        if unsafe { bindings::xmlSecKeyAdoptData(self.0, data) } < 0
+        {
+            unsafe { bindings::xmlSecKeyDataDestroy(data) };
+            return Err(XmlSecError::AllocationFailed("certificate data".into()));
+        }
+
+        Ok(count)
+    }
+
+    /// Hands the owned references of `certs` over to `data` for `adopt_chain`. Whatever made it into `data` before a
+    /// failure stays there for the caller to dispose of.
+    fn fill_chain(&self, data: bindings::xmlSecKeyDataPtr, mut certs: Vec<*mut bindings::X509>) -> XmlSecResult<()>
+    {
+        let own = self.evp_key().ok().and_then(|pkey| {
+            certs.iter().position(|&cert| certifies(cert, pkey))
+        });
+
+        let mut key_cert = null_mut();
+
+        if let Some(pos) = own
+        {
+            let cert = certs.remove(pos);
+            certs.insert(0, cert);
+
+            // a second reference, held by xmlsec as the key certificate
+            if unsafe { bindings::X509_up_ref(cert) } == 1 {
+                key_cert = cert;
+            }
+        }
+
+        let mut certs = certs.into_iter();
+
+        while let Some(cert) = certs.next()
+        {
+            if unsafe { bindings::xmlSecOpenSSLKeyDataX509AdoptCert(data, cert) } < 0
+            {
+                unsafe { bindings::X509_free(cert) };
+                free_certs(certs.chain(Some(key_cert).filter(|cert| !cert.is_null())).collect());
+                return Err(XmlSecError::CertLoadError);
+            }
+        }
+
+        if !key_cert.is_null() && unsafe { bindings::xmlSecOpenSSLKeyDataX509AdoptKeyCert(data, key_cert) } < 0
+        {
+            unsafe { bindings::X509_free(key_cert) };
+            return Err(XmlSecError::CertLoadError);
+        }
+
+        Ok(())
+    }
+
     /// Set name of the key.
-    pub fn set_name(&mut self, name: &str)
+    pub fn set_name(&mut self, name: &str) -> XmlSecResult<()>
     {
-        let cname = CString::new(name).unwrap();
+        let cname = cstring(name, "key name")?;
 
         let rc = unsafe { bindings::xmlSecKeySetName(
             self.0,
@@ -145,17 +822,114 @@ impl XmlSecKey
         ) };
 
         if rc < 0 {
-            panic!("Failed to set name for key");   // TODO proper error handling
+            return Err(XmlSecError::AllocationFailed("key name".into()));
+        }
+
+        Ok(())
+    }
+
+    /// Get the name currently set for the key, if any and valid UTF-8.
+    pub fn get_name(&self) -> Option<&str>
+    {
+        let raw = unsafe { bindings::xmlSecKeyGetName(self.0) };
+
+        if raw.is_null() {
+            return None;
+        }
+
+        unsafe { CStr::from_ptr(raw as *const c_char) }.to_str().ok()
+    }
+
+    /// The OpenSSL key pair held as the value of the key, still owned by it.
+    pub(crate) fn evp_key(&self) -> XmlSecResult<*mut bindings::EVP_PKEY>
+    {
+        let value = unsafe { bindings::xmlSecKeyGetValue(self.0) };
+
+        if !self.kind().is_some_and(|k| k.is_asymmetric()) {
+            return Err(XmlSecError::KeyExportError("key holds no asymmetric key pair".into()));
+        }
+
+        let pkey = unsafe { bindings::xmlSecOpenSSLEvpKeyDataGetEvp(value) };
+
+        if pkey.is_null() {
+            return Err(XmlSecError::KeyExportError("key holds no asymmetric key pair".into()));
+        }
+
+        Ok(pkey)
+    }
+
+    pub(crate) fn private_evp_key(&self) -> XmlSecResult<*mut bindings::EVP_PKEY>
+    {
+        let pkey = self.evp_key()?;
+
+        let kind = unsafe { bindings::xmlSecKeyGetType(self.0) };
+
+        if kind & bindings::xmlSecKeyDataTypePrivate == 0 {
+            return Err(XmlSecError::KeyExportError("key holds no private material".into()));
+        }
+
+        Ok(pkey)
+    }
+
+    /// Certificates held in the X509 data of the key, still owned by it.
+    pub(crate) fn x509_certs(&self) -> Vec<*mut bindings::X509>
+    {
+        let data = unsafe { bindings::xmlSecKeyGetData(self.0, bindings::xmlSecOpenSSLKeyDataX509GetKlass()) };
+
+        if data.is_null() {
+            return Vec::new();
+        }
+
+        let count = unsafe { bindings::xmlSecOpenSSLKeyDataX509GetCertsSize(data) };
+
+        (0..count)
+            .map(|pos| unsafe { bindings::xmlSecOpenSSLKeyDataX509GetCert(data, pos) })
+            .filter(|cert| !cert.is_null())
+            .collect()
+    }
+
+    /// Certificate at `index` among [`x509_certs`][Self::x509_certs], still owned by the key.
+    fn x509_cert_at(&self, index: usize) -> XmlSecResult<*mut bindings::X509>
+    {
+        self.x509_certs()
+            .get(index)
+            .copied()
+            .ok_or_else(|| XmlSecError::KeyExportError(format!("key holds no certificate at position {}", index)))
+    }
+
+    /// Raw bytes of a symmetric key, still owned by it.
+    fn symmetric_bytes(&self) -> Option<&[u8]>
+    {
+        if !self.kind().is_some_and(|k| !k.is_asymmetric()) {
+            return None;
+        }
+
+        let buffer = unsafe { bindings::xmlSecKeyDataBinaryValueGetBuffer(bindings::xmlSecKeyGetValue(self.0)) };
+
+        if buffer.is_null() {
+            return None;
+        }
+
+        let data = unsafe { bindings::xmlSecBufferGetData(buffer) };
+        let size = unsafe { bindings::xmlSecBufferGetSize(buffer) };
+
+        if data.is_null() {
+            return Some(&[]);
         }
+
+        Some(unsafe { std::slice::from_raw_parts(data, size as usize) })
     }
 
-    /// Get the name currently set for the key.
-    pub fn get_name(&self) -> &str
+    /// The certificate marked as the one of the key itself, still owned by it.
+    pub(crate) fn key_cert(&self) -> *mut bindings::X509
     {
-        let raw   = unsafe { bindings::xmlSecKeyGetName(self.0) };
-        let cname = unsafe { CStr::from_ptr(raw as *const c_char) };
+        let data = unsafe { bindings::xmlSecKeyGetData(self.0, bindings::xmlSecOpenSSLKeyDataX509GetKlass()) };
+
+        if data.is_null() {
+            return null_mut();
+        }
 
-        cname.to_str().unwrap()  // TODO proper error handling
+        unsafe { bindings::xmlSecOpenSSLKeyDataX509GetKeyCert(data) }
     }
 
     /// # Safety
@@ -193,11 +967,455 @@ impl XmlSecKey
 }
 
 
+fn detect_pem(text: &str) -> Option<XmlSecKeyFormat>
+{
+    let labels = text.split("-----BEGIN ")
+        .skip(1)
+        .filter_map(|block| block.split_once("-----"))
+        .map(|(label, _)| label);
+
+    let mut certificate = false;
+
+    for label in labels
+    {
+        match label
+        {
+            "PRIVATE KEY" | "ENCRYPTED PRIVATE KEY" => return Some(XmlSecKeyFormat::Pkcs8Pem),
+
+            // "RSA PUBLIC KEY" (PKCS#1) included, which the PEM loader takes along with SubjectPublicKeyInfo
+            l if l.ends_with("PRIVATE KEY") || l.ends_with("PUBLIC KEY") => return Some(XmlSecKeyFormat::Pem),
+
+            "CERTIFICATE" | "X509 CERTIFICATE" | "TRUSTED CERTIFICATE" => certificate = true,
+
+            _ => (),
+        }
+    }
+
+    certificate.then_some(XmlSecKeyFormat::CertPem)
+}
+
+
+fn detect_der(buffer: &[u8]) -> Option<XmlSecKeyFormat>
+{
+    let (tag, content) = der_outer(buffer)?;
+
+    if tag != DER_SEQUENCE {
+        return None;
+    }
+
+    let (first, version, rest) = der_element(content)?;
+
+    // only the tag of the second element matters, whose length may be indefinite within BER encoded PKCS#12
+    let second = *rest.first()?;
+
+    match (first, second)
+    {
+        // PFX { version 3, authSafe ContentInfo, .. }
+        (DER_INTEGER, DER_SEQUENCE) if version == [3] => Some(XmlSecKeyFormat::Pkcs12),
+
+        // PrivateKeyInfo { version, privateKeyAlgorithm AlgorithmIdentifier, privateKey }
+        (DER_INTEGER, DER_SEQUENCE) => Some(XmlSecKeyFormat::Pkcs8Der),
+
+        // traditional private key, starting out with its version followed by plain values
+        (DER_INTEGER, _) => Some(XmlSecKeyFormat::Der),
+
+        // EncryptedPrivateKeyInfo { encryptionAlgorithm, encryptedData }
+        (DER_SEQUENCE, DER_OCTET_STRING) => Some(XmlSecKeyFormat::Pkcs8Der),
+
+        // SubjectPublicKeyInfo { algorithm, subjectPublicKey }
+        (DER_SEQUENCE, DER_BIT_STRING) => Some(XmlSecKeyFormat::Der),
+
+        // Certificate { tbsCertificate, signatureAlgorithm, .. }
+        (DER_SEQUENCE, DER_SEQUENCE) => Some(XmlSecKeyFormat::CertDer),
+
+        _ => None,
+    }
+}
+
+
+/// Splits the outermost element of `data` into its tag and content. Unlike nested ones it may come in the BER
+/// indefinite length form closed by end-of-contents octets, as PKCS#12 files written by OpenSSL and Windows often do.
+fn der_outer(data: &[u8]) -> Option<(u8, &[u8])>
+{
+    match data
+    {
+        [tag, 0x80, content @ .., 0, 0] => Some((*tag, content)),
+
+        _ => der_element(data).map(|(tag, content, _)| (tag, content)),
+    }
+}
+
+
+/// xmlsec reads PKCS#8 DER as `EncryptedPrivateKeyInfo` only, hence a plain `PrivateKeyInfo` is handed to its DER
+/// loader instead, which takes any unencrypted private key.
+fn loader_format(buffer: &[u8], format: XmlSecKeyFormat) -> XmlSecKeyFormat
+{
+    let plain = der_outer(buffer)
+        .and_then(|(_, content)| der_element(content))
+        .is_some_and(|(tag, _, _)| tag == DER_INTEGER);
+
+    if format == XmlSecKeyFormat::Pkcs8Der && plain {
+        XmlSecKeyFormat::Der
+    } else {
+        format
+    }
+}
+
+
+/// Splits the leading DER element off `data`, into its tag, its content and whatever follows.
+fn der_element(data: &[u8]) -> Option<(u8, &[u8], &[u8])>
+{
+    let (&tag, data)   = data.split_first()?;
+    let (&first, data) = data.split_first()?;
+
+    let (length, data) = if first < 0x80 {
+        (first as usize, data)
+    } else {
+        let count = (first & 0x7F) as usize;
+
+        if count == 0 || count > std::mem::size_of::<u32>() || data.len() < count {
+            return None;
+        }
+
+        let (bytes, data) = data.split_at(count);
+
+        (bytes.iter().fold(0, |acc, &b| (acc << 8) | b as usize), data)
+    };
+
+    if data.len() < length {
+        return None;
+    }
+
+    let (content, rest) = data.split_at(length);
+
+    Some((tag, content, rest))
+}
+
+
+/// Reads every certificate of a PEM bundle, skipping blocks of other kinds such as keys. The references returned
+/// are owned by the caller.
+fn read_pem_certs(buffer: &[u8]) -> XmlSecResult<Vec<*mut bindings::X509>>
+{
+    let bio = unsafe { bindings::BIO_new_mem_buf(buffer.as_ptr() as *const c_void, buffer.len() as c_int) };
+
+    if bio.is_null() {
+        return Err(XmlSecError::AllocationFailed("memory BIO".into()));
+    }
+
+    let mut certs = Vec::new();
+
+    let ((), errors) = stack::capture(|| {
+        loop
+        {
+            let cert = unsafe { bindings::PEM_read_bio_X509(bio, null_mut(), None, null_mut()) };
+
+            if cert.is_null() {
+                break;
+            }
+
+            certs.push(cert);
+        }
+    });
+
+    unsafe { bindings::BIO_free(bio) };
+
+    // running out of certificates shows as a missing start line, anything else means a broken one
+    let broken = errors.openssl.iter()
+        .any(|e| e.lib != stack::ERR_LIB_PEM || e.reason != stack::PEM_R_NO_START_LINE);
+
+    if broken
+    {
+        free_certs(certs);
+        return Err(XmlSecError::CertLoadError);
+    }
+
+    Ok(certs)
+}
+
+
+/// Whether `cert` holds the public key of `pkey`.
+pub(crate) fn certifies(cert: *mut bindings::X509, pkey: *mut bindings::EVP_PKEY) -> bool
+{
+    unsafe { bindings::EVP_PKEY_eq(bindings::X509_get0_pubkey(cert), pkey) == 1 }
+}
+
+
+fn sha256(bytes: &[u8]) -> XmlSecResult<[u8; SHA256_LENGTH]>
+{
+    let mut digest = [0u8; SHA256_LENGTH];
+    let mut length = 0 as c_uint;
+
+    let rc = unsafe {
+        bindings::EVP_Digest(
+            bytes.as_ptr() as *const c_void,
+            bytes.len(),
+            digest.as_mut_ptr(),
+            &mut length,
+            bindings::EVP_sha256(),
+            null_mut()
+        )
+    };
+
+    if rc != 1 || length as usize != SHA256_LENGTH {
+        return Err(XmlSecError::KeyExportError("failed to digest key".into()));
+    }
+
+    Ok(digest)
+}
+
+
+fn free_certs(certs: Vec<*mut bindings::X509>)
+{
+    for cert in certs {
+        unsafe { bindings::X509_free(cert) };
+    }
+}
+
+
+/// Runs `write` against a fresh memory BIO and returns what it wrote. A `secure` BIO wipes its buffer when freed.
+fn write_bio<F>(secure: bool, write: F) -> XmlSecResult<Zeroizing<Vec<u8>>>
+where
+    F: FnOnce(*mut bindings::BIO) -> c_int
+{
+    let method = unsafe { if secure { bindings::BIO_s_secmem() } else { bindings::BIO_s_mem() } };
+    let bio    = unsafe { bindings::BIO_new(method) };
+
+    if bio.is_null() {
+        return Err(XmlSecError::AllocationFailed("memory BIO".into()));
+    }
+
+    let (rc, errors) = stack::capture(|| write(bio));
+
+    if rc <= 0
+    {
+        unsafe { bindings::BIO_free(bio) };
+        return Err(XmlSecError::KeyExportError(errors.to_string()));
+    }
+
+    let mut data: *mut c_char = null_mut();
+
+    // BIO_get_mem_data, a macro around BIO_ctrl
+    let len = unsafe {
+        bindings::BIO_ctrl(bio, bindings::BIO_CTRL_INFO as c_int, 0, &mut data as *mut *mut c_char as *mut c_void)
+    };
+
+    let bytes = if data.is_null() || len <= 0 {
+        Vec::new()
+    } else {
+        unsafe { std::slice::from_raw_parts(data as *const u8, len as usize) }.to_vec()
+    };
+
+    unsafe { bindings::BIO_free(bio) };
+
+    Ok(Zeroizing::new(bytes))
+}
+
+
+fn into_pem(mut bytes: Zeroizing<Vec<u8>>) -> XmlSecResult<Zeroizing<String>>
+{
+    // moved over rather than copied, so no trace of the buffer outlives its zeroizing owner
+    match String::from_utf8(std::mem::take(&mut *bytes))
+    {
+        Ok(pem) => Ok(Zeroizing::new(pem)),
+        Err(e)  => {
+            drop(Zeroizing::new(e.into_bytes()));
+            Err(XmlSecError::KeyExportError("PEM output is not valid UTF-8".into()))
+        }
+    }
+}
+
+
+/// Whether keys of `kind` may be `size` bits long.
+fn valid_size(kind: XmlSecKeyKind, size: u32) -> bool
+{
+    match kind
+    {
+        XmlSecKeyKind::Rsa  => size >= 512 && size % 8 == 0,
+        XmlSecKeyKind::Ec   => ec_curve(size).is_some(),
+        XmlSecKeyKind::Dsa  => false,
+        XmlSecKeyKind::Hmac => size > 0 && size % 8 == 0,
+        XmlSecKeyKind::Aes  => matches!(size, 128 | 192 | 256),
+        XmlSecKeyKind::Des3 => size == 192,
+    }
+}
+
+
+fn check_symmetric(kind: XmlSecKeyKind, length: usize) -> XmlSecResult<()>
+{
+    if kind.is_asymmetric() {
+        return Err(XmlSecError::KeyFormatError(format!("{:?} keys cannot be created from raw bytes", kind)));
+    }
+
+    let bits = u32::try_from(length).ok().and_then(|l| l.checked_mul(8));
+
+    if !bits.is_some_and(|b| valid_size(kind, b)) {
+        return Err(XmlSecError::KeyFormatError(format!("{} bytes are not valid for {:?} keys", length, kind)));
+    }
+
+    Ok(())
+}
+
+
+/// Name of the NIST curve with an order of `size` bits, as OpenSSL knows it.
+fn ec_curve(size: u32) -> Option<&'static [u8]>
+{
+    match size
+    {
+        256 => Some(b"P-256\0"),
+        384 => Some(b"P-384\0"),
+        521 => Some(b"P-521\0"),
+        _   => None,
+    }
+}
+
+
+/// Generates an EC key pair with OpenSSL, for xmlsec to adopt into freshly created key data.
+fn generate_ec(size: u32) -> XmlSecResult<XmlSecKey>
+{
+    let curve = ec_curve(size)
+        .ok_or_else(|| XmlSecError::KeyGenerationError(format!("no curve of {} bits", size)))?;
+
+    let (pkey, errors) = stack::capture(|| unsafe {
+        let ctx = bindings::EVP_PKEY_CTX_new_from_name(null_mut(), b"EC\0".as_ptr() as *const c_char, null());
+
+        if ctx.is_null() {
+            return null_mut();
+        }
+
+        let mut pkey = null_mut();
+
+        if bindings::EVP_PKEY_keygen_init(ctx) <= 0
+            || bindings::EVP_PKEY_CTX_set_group_name(ctx, curve.as_ptr() as *const c_char) <= 0
+            || bindings::EVP_PKEY_generate(ctx, &mut pkey) <= 0
+        {
+            pkey = null_mut();
+        }
+
+        bindings::EVP_PKEY_CTX_free(ctx);
+
+        pkey
+    });
+
+    if pkey.is_null() {
+        return Err(XmlSecError::KeyGenerationError(errors.to_string()));
+    }
+
+    let data = unsafe { bindings::xmlSecKeyDataCreate(XmlSecKeyKind::Ec.to_data_id()) };
+
+    if data.is_null()
+    {
+        unsafe { bindings::EVP_PKEY_free(pkey) };
+        return Err(XmlSecError::AllocationFailed("key data".into()));
+    }
+
+    if unsafe { bindings::xmlSecOpenSSLKeyDataEcdsaAdoptEvp(data, pkey) } < 0
+    {
+        unsafe { bindings::EVP_PKEY_free(pkey) };
+        unsafe { bindings::xmlSecKeyDataDestroy(data) };
+        return Err(XmlSecError::KeyGenerationError("key data refused the generated key pair".into()));
+    }
+
+    from_key_data(data)
+}
+
+
+/// Wraps `data` into a new key, which takes over its ownership.
+pub(crate) fn from_key_data(data: bindings::xmlSecKeyDataPtr) -> XmlSecResult<XmlSecKey>
+{
+    let key = unsafe { bindings::xmlSecKeyCreate() };
+
+    if key.is_null()
+    {
+        unsafe { bindings::xmlSecKeyDataDestroy(data) };
+        return Err(XmlSecError::AllocationFailed("key".into()));
+    }
+
+    if unsafe { bindings::xmlSecKeySetValue(key, data) } < 0
+    {
+        unsafe { bindings::xmlSecKeyDataDestroy(data) };
+        unsafe { bindings::xmlSecKeyDestroy(key) };
+        return Err(XmlSecError::AllocationFailed("key value".into()));
+    }
+
+    Ok(XmlSecKey(key))
+}
+
+
+/// xmlsec hands PKCS#12 input to OpenSSL with the static password only, leaving the callback unused. Hence for that
+/// format the provider gets asked up front.
+fn eager_password(format: &XmlSecKeyFormat, provider: &mut PasswordProvider) -> XmlSecResult<Option<Zeroizing<CString>>>
+{
+    if !matches!(format, XmlSecKeyFormat::Pkcs12) {
+        return Ok(None);
+    }
+
+    provider.password()
+        .map(|p| cstring(&p, "key password").map(Zeroizing::new))
+        .transpose()
+}
+
+
+/// Tells apart why xmlsec failed to load a key, judging by what it and OpenSSL reported meanwhile. OpenSSL leaves
+/// no trace of a mismatching PKCS#12 MAC, so that one is recognized by the xmlsec report alone.
+fn load_error(errors: ErrorStack) -> XmlSecError
+{
+    let io = errors.has_system_error()
+        || errors.has_xmlsec_subject("BIO_read_filename");
+
+    let password = errors.has_openssl(stack::ERR_LIB_EVP, &[stack::EVP_R_BAD_DECRYPT])
+        || errors.has_openssl(stack::ERR_LIB_PROV, &[stack::PROV_R_BAD_DECRYPT])
+        || errors.has_openssl(stack::ERR_LIB_PKCS12, &[
+            stack::PKCS12_R_MAC_VERIFY_FAILURE,
+            stack::PKCS12_R_PKCS12_CIPHERFINAL_ERROR,
+        ])
+        || errors.has_openssl(stack::ERR_LIB_PEM, &[
+            stack::PEM_R_BAD_DECRYPT,
+            stack::PEM_R_BAD_PASSWORD_READ,
+            stack::PEM_R_PROBLEMS_GETTING_PASSWORD,
+        ])
+        || errors.has_xmlsec_subject("PKCS12_verify_mac");
+
+    let detail = errors.to_string();
+
+    if io {
+        XmlSecError::KeyIoError(detail)
+    } else if password {
+        XmlSecError::KeyPasswordError(detail)
+    } else {
+        XmlSecError::KeyFormatError(detail)
+    }
+}
+
+
 impl PartialEq for XmlSecKey
 {
+    /// Keys are equal when of the same kind and holding the same key material, where a key pair differs from its
+    /// public half. Names, usage and certificates are left out of the comparison.
     fn eq(&self, other: &Self) -> bool
     {
-        self.0 == other.0  // compare pointer addresses
+        if self.0 == other.0 {
+            return true;
+        }
+
+        let kind = self.kind();
+
+        if kind.is_none() || kind != other.kind() || self.is_private() != other.is_private() {
+            return false;
+        }
+
+        if let (Ok(ours), Ok(theirs)) = (self.evp_key(), other.evp_key()) {
+            return unsafe { bindings::EVP_PKEY_eq(ours, theirs) } == 1;
+        }
+
+        match (self.symmetric_bytes(), other.symmetric_bytes())
+        {
+            // not to give away how much of a secret matched
+            (Some(ours), Some(theirs)) => {
+                ours.len() == theirs.len() && ours.iter().zip(theirs).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+            }
+
+            _ => false,
+        }
     }
 }
 
@@ -207,11 +1425,16 @@ impl Eq for XmlSecKey {}
 
 impl Clone for XmlSecKey
 {
+    /// Panics if xmlsec fails to duplicate the key, see [`try_clone`][try] for a fallible alternative.
+    ///
+    /// [try]: struct.XmlSecKey.html#method.try_clone
     fn clone(&self) -> Self
     {
-        let new = unsafe { bindings::xmlSecKeyDuplicate(self.0) };
-
-        Self(new)
+        match self.try_clone()
+        {
+            Ok(key) => key,
+            Err(e)  => panic!("Failed to clone XmlSecKey: {}", e),
+        }
     }
 }
 
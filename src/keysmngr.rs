@@ -0,0 +1,138 @@
+//!
+//! Wrapper for XmlSec Keys Manager
+//!
+use crate::bindings;
+
+use crate::XmlSecError;
+use crate::XmlSecKey;
+use crate::XmlSecKeyFormat;
+use crate::XmlSecResult;
+
+use crate::error::cstring;
+
+
+/// Keys and certificates for a [`XmlSecSignatureContext`][sigctx] to pick from, instead of a single key set in
+/// advance. Keys get looked up by `<dsig:KeyName/>` or by the requirements of the signature, while certificates
+/// either carry keys of their own or, when trusted, anchor the verification of certificate chains.
+///
+/// [sigctx]: struct.XmlSecSignatureContext.html
+#[derive(Debug)]
+pub struct XmlSecKeysManager(*mut bindings::xmlSecKeysMngr);
+
+
+impl XmlSecKeysManager
+{
+    /// Builds an empty manager backed by the default keys store and X509 store, ensuring xmlsec is initialized.
+    pub fn new() -> XmlSecResult<Self>
+    {
+        crate::xmlsec::guarantee_xmlsec_init()?;
+
+        let mngr = unsafe { bindings::xmlSecKeysMngrCreate() };
+
+        if mngr.is_null() {
+            return Err(XmlSecError::AllocationFailed("keys manager".into()));
+        }
+
+        if unsafe { bindings::xmlSecOpenSSLAppDefaultKeysMngrInit(mngr) } < 0
+        {
+            unsafe { bindings::xmlSecKeysMngrDestroy(mngr) };
+            return Err(XmlSecError::InitError("failed to set up the keys manager stores".into()));
+        }
+
+        Ok(Self(mngr))
+    }
+
+    /// Hands `key` over to the manager.
+    pub fn add_key(&mut self, key: XmlSecKey) -> XmlSecResult<()>
+    {
+        let key = unsafe { XmlSecKey::leak(key) };
+
+        if unsafe { bindings::xmlSecOpenSSLAppDefaultKeysMngrAdoptKey(self.0, key) } < 0
+        {
+            unsafe { bindings::xmlSecKeyDestroy(key) };
+            return Err(XmlSecError::AllocationFailed("keys store entry".into()));
+        }
+
+        Ok(())
+    }
+
+    /// Load certificate into the manager by specifying path and its format. Only `trusted` certificates anchor the
+    /// verification of certificate chains.
+    pub fn add_certificate_from_file(&mut self, path: &str, format: XmlSecKeyFormat, trusted: bool)
+        -> XmlSecResult<()>
+    {
+        let cpath = cstring(path, "certificate path")?;
+
+        let rc = unsafe {
+            bindings::xmlSecOpenSSLAppKeysMngrCertLoad(self.0, cpath.as_ptr(), format as u32, certificate_type(trusted))
+        };
+
+        if rc < 0 {
+            return Err(XmlSecError::CertLoadError);
+        }
+
+        Ok(())
+    }
+
+    /// Load certificate into the manager by specifying buffer to its contents. Only `trusted` certificates anchor
+    /// the verification of certificate chains.
+    pub fn add_certificate_from_memory(&mut self, buffer: &[u8], format: XmlSecKeyFormat, trusted: bool)
+        -> XmlSecResult<()>
+    {
+        let rc = unsafe {
+            bindings::xmlSecOpenSSLAppKeysMngrCertLoadMemory(
+                self.0,
+                buffer.as_ptr(),
+                buffer.len() as u32,
+                format as u32,
+                certificate_type(trusted)
+            )
+        };
+
+        if rc < 0 {
+            return Err(XmlSecError::CertLoadError);
+        }
+
+        Ok(())
+    }
+
+    /// # Safety
+    ///
+    /// Returns a raw pointer to the underlying xmlsec structure, which remains owned by this object.
+    pub unsafe fn as_ptr(&self) -> *mut bindings::xmlSecKeysMngr
+    {
+        self.0
+    }
+
+    /// The X509 store of the manager, holding its certificates.
+    #[cfg_attr(not(feature = "openssl"), allow(dead_code))]
+    pub(crate) fn x509_store(&self) -> XmlSecResult<*mut bindings::xmlSecKeyDataStore>
+    {
+        let store = unsafe { bindings::xmlSecKeysMngrGetDataStore(self.0, bindings::xmlSecOpenSSLX509StoreGetKlass()) };
+
+        if store.is_null() {
+            return Err(XmlSecError::InitError("keys manager lacks an X509 store".into()));
+        }
+
+        Ok(store)
+    }
+}
+
+
+impl Drop for XmlSecKeysManager
+{
+    fn drop(&mut self)
+    {
+        unsafe { bindings::xmlSecKeysMngrDestroy(self.0) };
+    }
+}
+
+
+pub(crate) fn certificate_type(trusted: bool) -> bindings::xmlSecKeyDataType
+{
+    if trusted {
+        bindings::xmlSecKeyDataTypeTrusted
+    } else {
+        bindings::xmlSecKeyDataTypeNone
+    }
+}
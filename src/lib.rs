@@ -21,13 +21,22 @@ pub use libxml::xpath::Context as XmlXPathContext;
 // internals
 mod exts;
 mod keys;
+mod keyinfo;
+mod keysmngr;
 mod error;
 mod crypto;
 mod xmlsec;
 mod xmldsig;
+mod inspect;
+mod x509;
+mod stack;
+mod password;
 mod templates;
 mod transforms;
 
+#[cfg(feature = "log")]
+mod logging;
+
 // exports
 pub mod bindings;  // for external extending of this wrapper
 
@@ -35,19 +44,48 @@ pub use self::exts::XmlSecDocumentExt;
 
 pub use self::keys::XmlSecKey;
 pub use self::keys::XmlSecKeyFormat;
+pub use self::keys::XmlSecKeyDataType;
+pub use self::keys::XmlSecKeyUsage;
+
+pub use self::keyinfo::XmlSecKeyInfoOptions;
+pub use self::keyinfo::XmlSecKeyInfoWriteOptions;
+
+pub use self::keysmngr::XmlSecKeysManager;
+
+pub use self::x509::XmlSecCertificateInfo;
 
 pub use self::error::XmlSecError;
 pub use self::error::XmlSecResult;
 
 pub use self::crypto::XmlSecSignatureMethod;
+pub use self::crypto::XmlSecKeyKind;
 
 pub use self::xmldsig::XmlSecSignatureContext;
+pub use self::xmldsig::XmlSecDSigStatus;
+pub use self::xmldsig::XmlSecReferenceResult;
+
+pub use self::inspect::SignatureInfo      as XmlSecSignatureInfo;
+pub use self::inspect::ReferenceInfo      as XmlSecReferenceInfo;
+pub use self::inspect::ReferenceTransform as XmlSecReferenceTransform;
+pub use self::inspect::KeyInfoContent     as XmlSecKeyInfoContent;
 
 pub use self::templates::TemplateBuilder            as XmlSecTemplateBuilder;
 pub use self::templates::XmlDocumentTemplating      as XmlSecDocumentTemplating;
 pub use self::templates::XmlDocumentTemplateBuilder as XmlSecDocumentTemplateBuilder;
+pub use self::templates::XmlNodeTemplating          as XmlSecNodeTemplating;
+pub use self::templates::XmlNodeTemplateBuilder     as XmlSecNodeTemplateBuilder;
+pub use self::templates::TemplatePlacement          as XmlSecTemplatePlacement;
+pub use self::templates::TemplateReference          as XmlSecTemplateReference;
+pub use self::templates::TemplateObject             as XmlSecTemplateObject;
+pub use self::templates::ObjectContent              as XmlSecTemplateObjectContent;
 
 pub use self::transforms::XmlSecCanonicalizationMethod;
+pub use self::transforms::XmlSecCanonicalize;
+pub use self::transforms::XmlSecTransformContext;
+pub use self::transforms::XmlSecTransformUsage;
+pub use self::transforms::XmlSecTransformMethod;
+pub use self::transforms::XmlSecCustomTransform;
+pub use self::transforms::XmlSecCustomTransformId;
 
 // export preambles
 pub mod template {
@@ -57,6 +95,11 @@ pub mod template {
         //! Preamble of all things signature template creation.
         pub use crate::XmlSecTemplateBuilder;
         pub use crate::XmlSecDocumentTemplating;
+        pub use crate::XmlSecNodeTemplating;
+        pub use crate::XmlSecTemplatePlacement;
+        pub use crate::XmlSecTemplateReference;
+        pub use crate::XmlSecTemplateObject;
+        pub use crate::XmlSecTemplateObjectContent;
         pub use crate::XmlSecCanonicalizationMethod;
         pub use crate::XmlSecSignatureMethod;
     }
@@ -0,0 +1,133 @@
+//!
+//! Routing of XmlSec Errors into the `log` Facade
+//!
+use crate::bindings;
+
+use log::kv::ToValue;
+use log::Level;
+use log::Record;
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::os::raw::c_int;
+
+use std::panic::catch_unwind;
+use std::panic::AssertUnwindSafe;
+
+
+/// Target of all records emitted on behalf of xmlsec
+const TARGET: &str = "xmlsec";
+
+
+/// Emits a `log` record in place of the default xmlsec error callback, which prints to stderr. Every record carries
+/// the xmlsec source location along with `function`, `object`, `subject` and `reason` as key-values. Verification
+/// failures of untrusted input, such as mismatching digests or unverifiable certificates, are expected outcomes and
+/// get logged as warnings. Anything else is an error. A panicking logger is stopped short of unwinding into xmlsec,
+/// losing the record.
+pub unsafe extern "C" fn log_error(
+    file:     *const c_char,
+    line:     c_int,
+    function: *const c_char,
+    object:   *const c_char,
+    subject:  *const c_char,
+    reason:   c_int,
+    message:  *const c_char,
+)
+{
+    let _ = catch_unwind(AssertUnwindSafe(|| emit(file, line, function, object, subject, reason, message)));
+}
+
+
+/// # Safety
+///
+/// All pointers must be null or point to NUL terminated strings, as handed over by xmlsec to its error callback.
+unsafe fn emit(
+    file:     *const c_char,
+    line:     c_int,
+    function: *const c_char,
+    object:   *const c_char,
+    subject:  *const c_char,
+    reason:   c_int,
+    message:  *const c_char,
+)
+{
+    let function = optional_str(function);
+    let level    = level_of(reason, function);
+
+    if level > log::max_level() {
+        return;
+    }
+
+    let message = optional_str(message)
+        .filter(|m| !m.is_empty())
+        .or_else(|| reason_text(reason))
+        .unwrap_or_default();
+
+    let kvs = [
+        ("function", function.to_value()),
+        ("object",   optional_str(object).to_value()),
+        ("subject",  optional_str(subject).to_value()),
+        ("reason",   reason.to_value()),
+    ];
+
+    log::logger().log(&Record::builder()
+        .args(format_args!("{}", message))
+        .level(level)
+        .target(TARGET)
+        .file(optional_str(file))
+        .line(u32::try_from(line).ok())
+        .key_values(&kvs)
+        .build());
+}
+
+
+fn level_of(reason: c_int, function: Option<&str>) -> Level
+{
+    let verifying = matches!(function, Some(f) if f.ends_with("Verify"));
+
+    let certificate = bindings::XMLSEC_ERRORS_R_CERT_VERIFY_FAILED..=bindings::XMLSEC_ERRORS_R_CERT_HAS_EXPIRED;
+
+    match reason as u32
+    {
+        bindings::XMLSEC_ERRORS_R_DATA_NOT_MATCH            => Level::Warn,
+        bindings::XMLSEC_ERRORS_R_INVALID_DATA if verifying => Level::Warn,
+        r if certificate.contains(&r)                       => Level::Warn,
+
+        _ => Level::Error,
+    }
+}
+
+
+/// Description xmlsec has for `reason`
+fn reason_text(reason: c_int) -> Option<&'static str>
+{
+    let mut pos = 0;
+
+    loop
+    {
+        let text = unsafe { bindings::xmlSecErrorsGetMsg(pos) };
+
+        if text.is_null() {
+            return None;
+        }
+
+        if unsafe { bindings::xmlSecErrorsGetCode(pos) } == reason {
+            return unsafe { optional_str(text) };
+        }
+
+        pos += 1;
+    }
+}
+
+
+/// # Safety
+///
+/// `ptr` must be null or point to a NUL terminated string living as long as `'a`.
+unsafe fn optional_str<'a>(ptr: *const c_char) -> Option<&'a str>
+{
+    if ptr.is_null() {
+        return None;
+    }
+
+    CStr::from_ptr(ptr).to_str().ok()
+}
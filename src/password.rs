@@ -0,0 +1,76 @@
+//!
+//! Bridging of Rust Password Providers into OpenSSL
+//!
+use std::os::raw::c_char;
+use std::os::raw::c_int;
+use std::os::raw::c_void;
+
+use std::panic::catch_unwind;
+use std::panic::AssertUnwindSafe;
+
+use zeroize::Zeroizing;
+
+
+/// Hands a password provider over to OpenSSL as the userdata of [`password_callback`].
+pub struct PasswordProvider<'a>
+{
+    provide: &'a mut dyn FnMut() -> Option<String>,
+}
+
+
+impl<'a> PasswordProvider<'a>
+{
+    pub fn new(provide: &'a mut dyn FnMut() -> Option<String>) -> Self
+    {
+        Self {provide}
+    }
+
+    /// Asks for the password straight away, for loaders that do not take a callback.
+    pub fn password(&mut self) -> Option<Zeroizing<String>>
+    {
+        catch_unwind(AssertUnwindSafe(|| (self.provide)()))
+            .ok()
+            .flatten()
+            .map(Zeroizing::new)
+    }
+
+    /// Userdata to pass alongside [`password_callback`]. Must not outlive `self`.
+    pub fn as_userdata(&mut self) -> *mut c_void
+    {
+        self as *mut Self as *mut c_void
+    }
+}
+
+
+/// A `pem_password_cb` asking the [`PasswordProvider`] given as `userdata`. OpenSSL only calls it once it finds the
+/// key to be encrypted. The password is wiped from Rust owned memory as soon as it has been copied into `buf`. A
+/// provider returning `None` or panicking, or a password not fitting in `buf`, aborts the load.
+///
+/// # Safety
+///
+/// `userdata` must come from [`PasswordProvider::as_userdata`] and `buf` must hold at least `size` bytes.
+pub unsafe extern "C" fn password_callback(
+    buf:      *mut c_char,
+    size:     c_int,
+    _rwflag:  c_int,
+    userdata: *mut c_void,
+) -> c_int
+{
+    let provider = &mut *(userdata as *mut PasswordProvider);
+
+    let password = match provider.password()
+    {
+        Some(password) => password,
+        None           => return -1,
+    };
+
+    let bytes = password.as_bytes();
+
+    if bytes.len() > usize::try_from(size).unwrap_or(0) {
+        return -1;
+    }
+
+    std::ptr::copy_nonoverlapping(bytes.as_ptr(), buf as *mut u8, bytes.len());
+
+    bytes.len() as c_int
+}
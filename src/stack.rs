@@ -0,0 +1,303 @@
+//!
+//! Error Stacks of XmlSec and OpenSSL
+//!
+use crate::bindings;
+
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::fmt;
+use std::os::raw::c_char;
+use std::os::raw::c_int;
+use std::os::raw::c_ulong;
+use std::ptr::null;
+
+
+// mirrors of the `ERR_GET_LIB`/`ERR_GET_REASON` internals of openssl/err.h, which are inline functions. The layout
+// is the one of OpenSSL 3, the oldest release bindings.rs accepts
+const ERR_SYSTEM_FLAG: c_ulong = 0x8000_0000;
+const ERR_LIB_OFFSET:  c_ulong = 23;
+const ERR_LIB_MASK:    c_ulong = 0xFF;
+const ERR_REASON_MASK: c_ulong = 0x7F_FFFF;
+
+// library and reason codes of openssl/err.h and the per-library headers
+pub const ERR_LIB_SYS:    c_int = 2;
+pub const ERR_LIB_EVP:    c_int = 6;
+pub const ERR_LIB_PEM:    c_int = 9;
+pub const ERR_LIB_PKCS12: c_int = 35;
+pub const ERR_LIB_PROV:   c_int = 57;
+
+pub const EVP_R_BAD_DECRYPT:                 c_int = 100;
+pub const PROV_R_BAD_DECRYPT:                c_int = 100;
+pub const PEM_R_BAD_DECRYPT:                 c_int = 101;
+pub const PEM_R_BAD_PASSWORD_READ:           c_int = 104;
+pub const PEM_R_NO_START_LINE:               c_int = 108;
+pub const PEM_R_PROBLEMS_GETTING_PASSWORD:   c_int = 109;
+pub const PKCS12_R_MAC_VERIFY_FAILURE:       c_int = 113;
+pub const PKCS12_R_PKCS12_CIPHERFINAL_ERROR: c_int = 116;
+
+
+thread_local! {
+    static CAPTURED: RefCell<Option<Vec<XmlSecErrorEntry>>> = const { RefCell::new(None) };
+}
+
+
+/// Errors reported by xmlsec and OpenSSL during a single call into xmlsec.
+#[derive(Debug, Default)]
+pub struct ErrorStack
+{
+    pub xmlsec:  Vec<XmlSecErrorEntry>,
+    pub openssl: Vec<OpenSslErrorEntry>,
+}
+
+
+/// A single report made by xmlsec through its error callback.
+#[derive(Debug)]
+pub struct XmlSecErrorEntry
+{
+    pub function: Option<String>,
+    pub subject:  Option<String>,
+    pub reason:   c_int,
+    pub message:  Option<String>,
+}
+
+
+/// A single entry of the OpenSSL error queue.
+#[derive(Debug)]
+pub struct OpenSslErrorEntry
+{
+    pub lib:    c_int,
+    pub reason: c_int,
+    pub text:   String,
+}
+
+
+/// An entry of the OpenSSL error queue held aside, to be queued again as it was.
+struct PendingError
+{
+    code: c_ulong,
+    file: *const c_char,
+    line: c_int,
+    func: *const c_char,
+    data: Option<CString>,
+}
+
+
+/// Replaces the default xmlsec error callback, so that errors can be captured on top of being reported.
+pub fn install()
+{
+    unsafe { bindings::xmlSecErrorsSetCallback(Some(report_error)) };
+}
+
+
+/// Runs `call` and collects everything xmlsec and OpenSSL reported meanwhile on this thread. Whatever was left in
+/// the OpenSSL queue beforehand is set aside during the call and queued again afterwards, for the caller to find.
+///
+/// OpenSSL marks would keep the earlier entries in place, but its queue only ever hands out the oldest entry, so the
+/// ones raised by `call` could not be read without popping the earlier ones first.
+pub fn capture<T>(call: impl FnOnce() -> T) -> (T, ErrorStack)
+{
+    let pending = take_pending();
+
+    let outer   = CAPTURED.with(|c| c.replace(Some(Vec::new())));
+    let result  = call();
+    let xmlsec  = CAPTURED.with(|c| c.replace(outer)).unwrap_or_default();
+    let openssl = drain_openssl();
+
+    requeue(pending);
+
+    (result, ErrorStack {xmlsec, openssl})
+}
+
+
+impl ErrorStack
+{
+    /// Whether OpenSSL queued an error of `reason` within `lib`
+    pub fn has_openssl(&self, lib: c_int, reasons: &[c_int]) -> bool
+    {
+        self.openssl.iter().any(|e| e.lib == lib && reasons.contains(&e.reason))
+    }
+
+    /// Whether OpenSSL queued a system error, as raised by failing I/O
+    pub fn has_system_error(&self) -> bool
+    {
+        self.openssl.iter().any(|e| e.lib == ERR_LIB_SYS)
+    }
+
+    /// Whether xmlsec reported a failure of `subject`, typically the OpenSSL function it called
+    pub fn has_xmlsec_subject(&self, subject: &str) -> bool
+    {
+        self.xmlsec.iter().any(|e| e.subject.as_deref() == Some(subject))
+    }
+}
+
+
+impl fmt::Display for ErrorStack
+{
+    /// All xmlsec reports, followed by the distinct OpenSSL errors, separated by semicolons.
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result
+    {
+        let mut parts = self.xmlsec.iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>();
+
+        for entry in &self.openssl
+        {
+            if !parts.contains(&entry.text) {
+                parts.push(entry.text.clone());
+            }
+        }
+
+        write!(fmt, "{}", parts.join("; "))
+    }
+}
+
+
+impl fmt::Display for XmlSecErrorEntry
+{
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result
+    {
+        let parts = [&self.function, &self.subject, &self.message]
+            .into_iter()
+            .flatten()
+            .map(|p| p.trim())
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<_>>();
+
+        write!(fmt, "{}", parts.join(": "))
+    }
+}
+
+
+unsafe extern "C" fn report_error(
+    file:     *const c_char,
+    line:     c_int,
+    function: *const c_char,
+    object:   *const c_char,
+    subject:  *const c_char,
+    reason:   c_int,
+    message:  *const c_char,
+)
+{
+    // the callback may fire while this thread is being torn down
+    let _ = CAPTURED.try_with(|c| {
+        if let Some(entries) = c.borrow_mut().as_mut()
+        {
+            entries.push(XmlSecErrorEntry {
+                function: optional_string(function),
+                subject:  optional_string(subject),
+                message:  optional_string(message),
+                reason,
+            });
+        }
+    });
+
+    #[cfg(feature = "log")]
+    crate::logging::log_error(file, line, function, object, subject, reason, message);
+
+    #[cfg(not(feature = "log"))]
+    bindings::xmlSecErrorsDefaultCallback(file, line, function, object, subject, reason, message);
+}
+
+
+fn drain_openssl() -> Vec<OpenSslErrorEntry>
+{
+    let mut entries = Vec::new();
+
+    loop
+    {
+        let code = unsafe { bindings::ERR_get_error() };
+
+        if code == 0 {
+            return entries;
+        }
+
+        let mut buffer = [0 as c_char; 256];
+
+        unsafe { bindings::ERR_error_string_n(code, buffer.as_mut_ptr(), buffer.len()) };
+
+        let (lib, reason) = split_code(code);
+
+        let text = unsafe { CStr::from_ptr(buffer.as_ptr()) }.to_string_lossy().into_owned();
+
+        entries.push(OpenSslErrorEntry {lib, reason, text});
+    }
+}
+
+
+/// Pops the whole OpenSSL error queue of this thread, keeping everything needed to queue it again.
+fn take_pending() -> Vec<PendingError>
+{
+    let mut pending = Vec::new();
+
+    loop
+    {
+        let mut file  = null();
+        let mut line  = 0;
+        let mut func  = null();
+        let mut data  = null();
+        let mut flags = 0;
+
+        let code = unsafe { bindings::ERR_get_error_all(&mut file, &mut line, &mut func, &mut data, &mut flags) };
+
+        if code == 0 {
+            return pending;
+        }
+
+        // the text stays owned by the queue, which reuses its slot for the next error
+        let data = if !data.is_null() && flags & bindings::ERR_TXT_STRING as c_int != 0 {
+            Some(unsafe { CStr::from_ptr(data) }.to_owned())
+        } else {
+            None
+        };
+
+        pending.push(PendingError {code, file, line, func, data});
+    }
+}
+
+
+/// Queues entries taken by `take_pending` again, in their original order. File and function names are static
+/// strings of OpenSSL, so that their pointers remain valid.
+fn requeue(pending: Vec<PendingError>)
+{
+    for entry in pending
+    {
+        let (lib, reason) = split_code(entry.code);
+
+        unsafe
+        {
+            bindings::ERR_new();
+            bindings::ERR_set_debug(entry.file, entry.line, entry.func);
+
+            match &entry.data
+            {
+                Some(data) => bindings::ERR_set_error(lib, reason, b"%s\0".as_ptr() as *const c_char, data.as_ptr()),
+                None       => bindings::ERR_set_error(lib, reason, null()),
+            }
+        }
+    }
+}
+
+
+/// Library and reason of an OpenSSL error code, system errors carrying the `errno` as reason.
+fn split_code(code: c_ulong) -> (c_int, c_int)
+{
+    if code & ERR_SYSTEM_FLAG != 0 {
+        (ERR_LIB_SYS, (code & !ERR_SYSTEM_FLAG) as c_int)
+    } else {
+        (((code >> ERR_LIB_OFFSET) & ERR_LIB_MASK) as c_int, (code & ERR_REASON_MASK) as c_int)
+    }
+}
+
+
+/// # Safety
+///
+/// `ptr` must be null or point to a NUL terminated string.
+unsafe fn optional_string(ptr: *const c_char) -> Option<String>
+{
+    if ptr.is_null() {
+        return None;
+    }
+
+    Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+}
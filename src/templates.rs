@@ -3,17 +3,41 @@
 //!
 use crate::bindings;
 
+use crate::XmlNode;
 use crate::XmlDocument;
+use crate::XmlXPathContext;
 
 use crate::XmlSecCanonicalizationMethod;
+use crate::XmlSecCustomTransformId;
 use crate::XmlSecSignatureMethod;
 
 use crate::XmlSecError;
 use crate::XmlSecResult;
 
+use crate::error::cstring;
+use crate::exts::register_namespaces;
+
+use std::ffi::CStr;
 use std::ffi::CString;
+use std::os::raw::c_char;
 use std::os::raw::c_uchar;
 use std::ptr::null;
+use std::ptr::null_mut;
+
+pub(crate) const DSIG11_NS:        &[u8] = b"http://www.w3.org/2009/xmldsig11#\0";
+pub(crate) const NODE_X509_DIGEST: &[u8] = b"X509Digest\0";
+const NODE_KEYINFO_REFERENCE: &[u8] = b"KeyInfoReference\0";
+const NODE_SIGNATURE_PROPERTY: &[u8] = b"SignatureProperty\0";
+
+type X509DataChildFn = unsafe extern "C" fn(bindings::xmlNodePtr) -> bindings::xmlNodePtr;
+
+type ReferenceAddFn = unsafe extern "C" fn(
+    bindings::xmlNodePtr,
+    bindings::xmlSecTransformId,
+    *const c_uchar,
+    *const c_uchar,
+    *const c_uchar,
+) -> bindings::xmlNodePtr;
 
 /// Declaration of a template building API for other specific trait extensions
 /// on foreign XML objects.
@@ -24,6 +48,10 @@ pub trait TemplateBuilder
     /// [c14n]: ./transforms/enum.XmlSecCanonicalizationMethod.html
     fn canonicalization(self, c14n: XmlSecCanonicalizationMethod) -> Self;
 
+    /// Sets the whitespace separated `PrefixList` of an `<ec:InclusiveNamespaces/>` node added to the
+    /// `<dsig:CanonicalizationMethod/>`. Requires one of the exclusive canonicalization methods.
+    fn inclusive_namespaces(self, prefixes: &str) -> Self;
+
     /// Sets cryptographic signature method. See: [`XmlSecSignatureMethod`][sig].
     ///
     /// [sig]: ./crypto/openssl/enum.XmlSecSignatureMethod.html
@@ -34,12 +62,54 @@ pub trait TemplateBuilder
     /// [sig]: ./crypto/openssl/enum.XmlSecSignatureMethod.html
     fn reference_signature(self, sig: XmlSecSignatureMethod) -> Self;
 
+    /// Appends a canonicalization transform to the `<dsig:Reference/>`, right after the enveloped transform. See:
+    /// [`XmlSecCanonicalizationMethod`][c14n].
+    ///
+    /// [c14n]: ./transforms/enum.XmlSecCanonicalizationMethod.html
+    fn reference_canonicalization(self, c14n: XmlSecCanonicalizationMethod) -> Self;
+
+    /// Sets the whitespace separated `PrefixList` of an `<ec:InclusiveNamespaces/>` node added to the reference
+    /// canonicalization transform. Requires an exclusive method to be set with `reference_canonicalization`.
+    fn reference_inclusive_namespaces(self, prefixes: &str) -> Self;
+
     /// Sets signature subject node URI
     fn uri(self, uri: &str) -> Self;
 
     /// the namespace prefix for the signature element (e.g. "dsig")
     fn ns_prefix(self, ns_prefix: &str) -> Self;
 
+    /// Sets the `Id` attribute of `<dsig:Signature/>`
+    fn signature_id(self, id: &str) -> Self;
+
+    /// Sets the `Id` attribute of `<dsig:SignedInfo/>`
+    fn signed_info_id(self, id: &str) -> Self;
+
+    /// Sets the `Id` attribute of `<dsig:SignatureValue/>`
+    fn signature_value_id(self, id: &str) -> Self;
+
+    /// Sets the `Id` attribute of `<dsig:KeyInfo/>`
+    fn keyinfo_id(self, id: &str) -> Self;
+
+    /// Sets the `Id` attribute of `<dsig:Reference/>`
+    fn reference_id(self, id: &str) -> Self;
+
+    /// Sets the `Type` attribute of `<dsig:Reference/>` (e.g. "http://uri.etsi.org/01903#SignedProperties")
+    fn reference_type(self, reftype: &str) -> Self;
+
+    /// Whether `<dsig:Reference/>` gets the enveloped signature transform, true by default. Enveloping signatures
+    /// referencing one of their own `<dsig:Object/>` nodes should disable it.
+    fn enveloped(self, add: bool) -> Self;
+
+    /// Adds a further `<dsig:Reference/>` to `<dsig:SignedInfo/>`. See: [`TemplateReference`][reference].
+    ///
+    /// [reference]: ./templates/struct.TemplateReference.html
+    fn reference(self, reference: TemplateReference) -> Self;
+
+    /// Appends a `<dsig:Object/>` to the signature. See: [`TemplateObject`][object].
+    ///
+    /// [object]: ./templates/struct.TemplateObject.html
+    fn object(self, object: TemplateObject) -> Self;
+
     /// Adds <ds:KeyName> to key information node
     fn keyname(self, add: bool) -> Self;
 
@@ -49,6 +119,40 @@ pub trait TemplateBuilder
     /// Adds <ds:X509Data> to key information node
     fn x509data(self, add: bool) -> Self;
 
+    /// Adds <ds:KeyName> holding the given name to key information node. Signing overwrites it with the name of the
+    /// key, if the latter has one.
+    fn keyname_text(self, name: &str) -> Self;
+
+    /// Adds <ds:X509Certificate> to <ds:X509Data>, filled in by signing with the certificates of the key
+    fn x509_certificate(self, add: bool) -> Self;
+
+    /// Adds <ds:X509SubjectName> to <ds:X509Data>, filled in by signing from the key certificate
+    fn x509_subject_name(self, add: bool) -> Self;
+
+    /// Adds <ds:X509IssuerSerial> to <ds:X509Data>, filled in by signing from the key certificate
+    fn x509_issuer_serial(self, add: bool) -> Self;
+
+    /// Adds <ds:X509SKI> to <ds:X509Data>, filled in by signing from the key certificate
+    fn x509_ski(self, add: bool) -> Self;
+
+    /// Adds <ds:X509CRL> to <ds:X509Data>, filled in by signing with the CRLs attached to the key
+    fn x509_crl(self, add: bool) -> Self;
+
+    /// Adds <dsig11:X509Digest> to <ds:X509Data> with the given digest algorithm, filled in by signing with the digest
+    /// of the certificate of the key. Building the template fails unless `digest` is a plain digest method.
+    fn x509_digest(self, digest: XmlSecSignatureMethod) -> Self;
+
+    /// Adds <ds:RetrievalMethod> with URI and optionally Type to key information node
+    fn retrieval_method(self, uri: &str, rmtype: Option<&str>) -> Self;
+
+    /// Adds <dsig11:KeyInfoReference> with URI to key information node
+    fn keyinfo_reference(self, uri: &str) -> Self;
+
+    /// Sets where the `<dsig:Signature/>` node gets inserted. See: [`TemplatePlacement`][place].
+    ///
+    /// [place]: ./templates/enum.TemplatePlacement.html
+    fn placement(self, placement: TemplatePlacement) -> Self;
+
     /// Builds the actual template and returns
     fn done(self) -> XmlSecResult<()>;
 }
@@ -71,19 +175,250 @@ pub struct XmlDocumentTemplateBuilder<'d>
     options: TemplateOptions,
 }
 
+/// Trait extension aimed at a concrete implementation for [`XmlNode`][xmlnode]. Placement of the signature is then
+/// relative to this node instead of the document root.
+///
+/// [xmlnode]: http://kwarc.github.io/rust-libxml/libxml/tree/node/struct.Node.html
+pub trait XmlNodeTemplating<'n>
+{
+    /// Return a template builder over current XmlNode.
+    fn template(&'n self) -> XmlNodeTemplateBuilder<'n>;
+}
+
+/// Concrete template builder for [`XmlNode`][xmlnode]
+///
+/// [xmlnode]: http://kwarc.github.io/rust-libxml/libxml/tree/node/struct.Node.html
+pub struct XmlNodeTemplateBuilder<'n>
+{
+    node:    &'n XmlNode,
+    options: TemplateOptions,
+}
+
+/// Where to insert the `<dsig:Signature/>` node. Child placements are relative to the templated node, which is the
+/// document root when templating a whole [`XmlDocument`][xmldoc].
+///
+/// [xmldoc]: http://kwarc.github.io/rust-libxml/libxml/tree/document/struct.Document.html
+pub enum TemplatePlacement
+{
+    /// Insert as first child of the templated node.
+    FirstChild,
+
+    /// Insert as last child of the templated node. This is the default.
+    LastChild,
+
+    /// Insert as preceding sibling of the given node (e.g. `<saml:Issuer/>`'s successor).
+    Before(XmlNode),
+
+    /// Insert as following sibling of the given node (e.g. right after `<saml:Issuer/>`).
+    After(XmlNode),
+
+    /// Insert as last child of the single node selected by an XPath expression, evaluated relative to the templated
+    /// node. Namespace prefixes used in the expression are bound by the given `(prefix, href)` pairs.
+    XPath(String, Vec<(String, String)>),
+}
+
+/// Additional `<dsig:Reference/>`, either in `<dsig:SignedInfo/>` or in a `<dsig:Manifest/>`. Digested with SHA1 and
+/// without any transform unless told otherwise.
+pub struct TemplateReference
+{
+    uri:     Option<String>,
+    id:      Option<String>,
+    reftype: Option<String>,
+    digest:  XmlSecSignatureMethod,
+
+    enveloped:     bool,
+    c14n:          Option<XmlSecCanonicalizationMethod>,
+    c14n_prefixes: Option<String>,
+    base64:        bool,
+    custom:        Vec<XmlSecCustomTransformId>,
+}
+
+/// `<dsig:Object/>` embedded in the signature, which turns it into an enveloping one when referenced.
+pub struct TemplateObject
+{
+    id:        Option<String>,
+    mime_type: Option<String>,
+    encoding:  Option<String>,
+    content:   ObjectContent,
+}
+
+/// What a [`TemplateObject`][object] carries.
+///
+/// [object]: ./templates/struct.TemplateObject.html
+pub enum ObjectContent
+{
+    /// Deep copy of the given node, which may belong to any document.
+    Xml(XmlNode),
+
+    /// Raw bytes, embedded base64 encoded. The `Encoding` attribute defaults to `http://www.w3.org/2000/09/xmldsig#base64`.
+    Binary(Vec<u8>),
+
+    /// `<dsig:SignatureProperties/>` with an optional `Id`, holding a `<dsig:SignatureProperty/>` with a deep copy of
+    /// the node for each `(target, node)` pair.
+    SignatureProperties(Option<String>, Vec<(String, XmlNode)>),
+
+    /// `<dsig:Manifest/>` with an optional `Id` and its references.
+    Manifest(Option<String>, Vec<TemplateReference>),
+}
+
+impl TemplateReference
+{
+    /// New reference towards `uri`, e.g. `#object-id` or `""` for the whole document.
+    pub fn new(uri: &str) -> Self
+    {
+        Self {
+            uri:     Some(uri.to_owned()),
+            id:      None,
+            reftype: None,
+            digest:  XmlSecSignatureMethod::Sha1,
+
+            enveloped:     false,
+            c14n:          None,
+            c14n_prefixes: None,
+            base64:        false,
+            custom:        Vec::new(),
+        }
+    }
+
+    /// Sets the digest method. See: [`XmlSecSignatureMethod`][sig].
+    ///
+    /// [sig]: ./crypto/openssl/enum.XmlSecSignatureMethod.html
+    pub fn digest(mut self, digest: XmlSecSignatureMethod) -> Self
+    {
+        self.digest = digest;
+        self
+    }
+
+    /// Sets the `Id` attribute
+    pub fn id(mut self, id: &str) -> Self
+    {
+        self.id = Some(id.to_owned());
+        self
+    }
+
+    /// Sets the `Type` attribute (e.g. "http://www.w3.org/2000/09/xmldsig#Manifest")
+    pub fn reference_type(mut self, reftype: &str) -> Self
+    {
+        self.reftype = Some(reftype.to_owned());
+        self
+    }
+
+    /// Adds the enveloped signature transform, first in the transform chain
+    pub fn enveloped(mut self, add: bool) -> Self
+    {
+        self.enveloped = add;
+        self
+    }
+
+    /// Adds a canonicalization transform, after the enveloped one. See: [`XmlSecCanonicalizationMethod`][c14n].
+    ///
+    /// [c14n]: ./transforms/enum.XmlSecCanonicalizationMethod.html
+    pub fn canonicalization(mut self, c14n: XmlSecCanonicalizationMethod) -> Self
+    {
+        self.c14n = Some(c14n);
+        self
+    }
+
+    /// Sets the `PrefixList` of the canonicalization transform, which must then be an exclusive one.
+    pub fn inclusive_namespaces(mut self, prefixes: &str) -> Self
+    {
+        self.c14n_prefixes = Some(prefixes.to_owned());
+        self
+    }
+
+    /// Adds a base64 decoding transform, last in the transform chain, so that the digest covers the decoded bytes of
+    /// a [`ObjectContent::Binary`][binary] object.
+    ///
+    /// [binary]: ./templates/enum.ObjectContent.html#variant.Binary
+    pub fn base64(mut self, add: bool) -> Self
+    {
+        self.base64 = add;
+        self
+    }
+
+    /// Adds a registered custom transform, after all of the above. Custom transforms apply in the order they were
+    /// added. See: [`XmlSecCustomTransformId`][custom].
+    ///
+    /// [custom]: ./transforms/struct.XmlSecCustomTransformId.html
+    pub fn transform(mut self, transform: XmlSecCustomTransformId) -> Self
+    {
+        self.custom.push(transform);
+        self
+    }
+}
+
+impl TemplateObject
+{
+    /// New object wrapping the given content
+    pub fn new(content: ObjectContent) -> Self
+    {
+        Self {id: None, mime_type: None, encoding: None, content}
+    }
+
+    /// Sets the `Id` attribute, by which references point at this object
+    pub fn id(mut self, id: &str) -> Self
+    {
+        self.id = Some(id.to_owned());
+        self
+    }
+
+    /// Sets the `MimeType` attribute (e.g. "text/plain")
+    pub fn mime_type(mut self, mime_type: &str) -> Self
+    {
+        self.mime_type = Some(mime_type.to_owned());
+        self
+    }
+
+    /// Sets the `Encoding` attribute
+    pub fn encoding(mut self, encoding: &str) -> Self
+    {
+        self.encoding = Some(encoding.to_owned());
+        self
+    }
+}
+
 struct TemplateOptions
 {
-    c14n: XmlSecCanonicalizationMethod,
+    c14n:          XmlSecCanonicalizationMethod,
+    c14n_prefixes: Option<String>,
 
     sig:    XmlSecSignatureMethod,
     refsig: XmlSecSignatureMethod,
 
+    refc14n:          Option<XmlSecCanonicalizationMethod>,
+    refc14n_prefixes: Option<String>,
+
     ns_prefix: Option<String>,
     uri:       Option<String>,
+    reftype:   Option<String>,
+
+    signature_id:       Option<String>,
+    signed_info_id:     Option<String>,
+    signature_value_id: Option<String>,
+    keyinfo_id:         Option<String>,
+    reference_id:       Option<String>,
+
+    enveloped:  bool,
+    references: Vec<TemplateReference>,
+    objects:    Vec<TemplateObject>,
 
     keyname:  bool,
     keyvalue: bool,
     x509data: bool,
+
+    keyname_text: Option<String>,
+
+    x509_certificate:   bool,
+    x509_subject_name:  bool,
+    x509_issuer_serial: bool,
+    x509_ski:           bool,
+    x509_crl:           bool,
+    x509_digest:        Option<XmlSecSignatureMethod>,
+
+    retrieval_method:  Option<(String, Option<String>)>,
+    keyinfo_reference: Option<String>,
+
+    placement: TemplatePlacement,
 }
 
 impl Default for TemplateOptions
@@ -91,17 +426,46 @@ impl Default for TemplateOptions
     fn default() -> Self
     {
         Self {
-            c14n: XmlSecCanonicalizationMethod::ExclusiveC14N,
+            c14n:          XmlSecCanonicalizationMethod::ExclusiveC14N,
+            c14n_prefixes: None,
 
             sig:    XmlSecSignatureMethod::RsaSha1,
             refsig: XmlSecSignatureMethod::Sha1,
 
+            refc14n:          None,
+            refc14n_prefixes: None,
+
             uri:       None,
             ns_prefix: None,
+            reftype:   None,
+
+            signature_id:       None,
+            signed_info_id:     None,
+            signature_value_id: None,
+            keyinfo_id:         None,
+            reference_id:       None,
+
+            enveloped:  true,
+            references: Vec::new(),
+            objects:    Vec::new(),
 
             keyname:  false,
             keyvalue: false,
             x509data: false,
+
+            keyname_text: None,
+
+            x509_certificate:   false,
+            x509_subject_name:  false,
+            x509_issuer_serial: false,
+            x509_ski:           false,
+            x509_crl:           false,
+            x509_digest:        None,
+
+            retrieval_method:  None,
+            keyinfo_reference: None,
+
+            placement: TemplatePlacement::LastChild,
         }
     }
 }
@@ -110,153 +474,1004 @@ impl<'d> XmlDocumentTemplating<'d> for XmlDocument
 {
     fn template(&'d self) -> XmlDocumentTemplateBuilder<'d>
     {
-        crate::xmlsec::guarantee_xmlsec_init();
-
         XmlDocumentTemplateBuilder {doc: self, options: TemplateOptions::default()}
     }
 }
 
-impl<'d> TemplateBuilder for XmlDocumentTemplateBuilder<'d>
+impl<'n> XmlNodeTemplating<'n> for XmlNode
 {
-    fn canonicalization(mut self, c14n: XmlSecCanonicalizationMethod) -> Self
+    fn template(&'n self) -> XmlNodeTemplateBuilder<'n>
     {
-        self.options.c14n = c14n;
-        self
+        XmlNodeTemplateBuilder {node: self, options: TemplateOptions::default()}
     }
+}
 
-    fn signature(mut self, sig: XmlSecSignatureMethod) -> Self
-    {
-        self.options.sig = sig;
-        self
-    }
+/// Option setters are the same for every concrete builder, only `done` differs in how the subject is resolved.
+macro_rules! impl_template_options {
+    () => {
+        fn canonicalization(mut self, c14n: XmlSecCanonicalizationMethod) -> Self
+        {
+            self.options.c14n = c14n;
+            self
+        }
 
-    fn reference_signature(mut self, sig: XmlSecSignatureMethod) -> Self
-    {
-        self.options.refsig = sig;
-        self
-    }
+        fn inclusive_namespaces(mut self, prefixes: &str) -> Self
+        {
+            self.options.c14n_prefixes = Some(prefixes.to_owned());
+            self
+        }
 
-    fn uri(mut self, uri: &str) -> Self
-    {
-        self.options.uri = Some(uri.to_owned());
-        self
-    }
+        fn signature(mut self, sig: XmlSecSignatureMethod) -> Self
+        {
+            self.options.sig = sig;
+            self
+        }
 
-    fn ns_prefix(mut self, ns_prefix: &str) -> Self
-    {
-        self.options.ns_prefix = Some(ns_prefix.to_owned());
-        self
-    }
+        fn reference_signature(mut self, sig: XmlSecSignatureMethod) -> Self
+        {
+            self.options.refsig = sig;
+            self
+        }
 
-    fn keyname(mut self, add: bool) -> Self
-    {
-        self.options.keyname = add;
-        self
-    }
+        fn reference_canonicalization(mut self, c14n: XmlSecCanonicalizationMethod) -> Self
+        {
+            self.options.refc14n = Some(c14n);
+            self
+        }
 
-    fn keyvalue(mut self, add: bool) -> Self
-    {
-        self.options.keyvalue = add;
-        self
-    }
+        fn reference_inclusive_namespaces(mut self, prefixes: &str) -> Self
+        {
+            self.options.refc14n_prefixes = Some(prefixes.to_owned());
+            self
+        }
 
-    fn x509data(mut self, add: bool) -> Self
-    {
-        self.options.x509data = add;
-        self
-    }
+        fn uri(mut self, uri: &str) -> Self
+        {
+            self.options.uri = Some(uri.to_owned());
+            self
+        }
 
-    fn done(self) -> XmlSecResult<()>
-    {
-        let curi = {
-            if let Some(uri) = self.options.uri {
-                CString::new(uri).unwrap().into_raw() as *const c_uchar
-            } else {
-                null()
-            }
-        };
+        fn ns_prefix(mut self, ns_prefix: &str) -> Self
+        {
+            self.options.ns_prefix = Some(ns_prefix.to_owned());
+            self
+        }
 
-        let c_ns_prefix = {
-            if let Some(ns_prefix) = self.options.ns_prefix {
-                CString::new(ns_prefix).unwrap().into_raw() as *const c_uchar
-            } else {
-                null()
-            }
-        };
+        fn signature_id(mut self, id: &str) -> Self
+        {
+            self.options.signature_id = Some(id.to_owned());
+            self
+        }
 
-        let docptr = self.doc.doc_ptr() as *mut bindings::xmlDoc;
+        fn signed_info_id(mut self, id: &str) -> Self
+        {
+            self.options.signed_info_id = Some(id.to_owned());
+            self
+        }
 
-        let rootptr = if let Some(root) = self.doc.get_root_element() {
-            root.node_ptr() as *mut bindings::xmlNode
-        } else {
-            return Err(XmlSecError::RootNotFound);
-        };
+        fn signature_value_id(mut self, id: &str) -> Self
+        {
+            self.options.signature_value_id = Some(id.to_owned());
+            self
+        }
 
-        let signature = unsafe { bindings::xmlSecTmplSignatureCreateNsPref(
-            docptr,
-            self.options.c14n.to_method(),
-            self.options.sig.to_method(),
-            null(),
-            c_ns_prefix,
-        ) };
+        fn keyinfo_id(mut self, id: &str) -> Self
+        {
+            self.options.keyinfo_id = Some(id.to_owned());
+            self
+        }
 
-        if signature.is_null() {
-            panic!("Failed to create signature template");
+        fn reference_id(mut self, id: &str) -> Self
+        {
+            self.options.reference_id = Some(id.to_owned());
+            self
         }
 
-        let reference = unsafe { bindings::xmlSecTmplSignatureAddReference(
-            signature,
-            self.options.refsig.to_method(),
-            null(),
-            curi,
-            null(),
-        ) };
+        fn reference_type(mut self, reftype: &str) -> Self
+        {
+            self.options.reftype = Some(reftype.to_owned());
+            self
+        }
 
-        if reference.is_null() {
-            panic!("Failed to add enveloped transform to reference");
+        fn enveloped(mut self, add: bool) -> Self
+        {
+            self.options.enveloped = add;
+            self
         }
 
-        let envelope = unsafe { bindings::xmlSecTmplReferenceAddTransform(reference, bindings::xmlSecTransformEnvelopedGetKlass()) };
+        fn reference(mut self, reference: TemplateReference) -> Self
+        {
+            self.options.references.push(reference);
+            self
+        }
 
-        if envelope.is_null() {
-            panic!("Failed to add enveloped transform")
+        fn object(mut self, object: TemplateObject) -> Self
+        {
+            self.options.objects.push(object);
+            self
         }
 
-        let keyinfo = unsafe { bindings::xmlSecTmplSignatureEnsureKeyInfo(signature, null()) };
+        fn keyname(mut self, add: bool) -> Self
+        {
+            self.options.keyname = add;
+            self
+        }
 
-        if keyinfo.is_null() {
-            panic!("Failed to ensure key info");
+        fn keyvalue(mut self, add: bool) -> Self
+        {
+            self.options.keyvalue = add;
+            self
         }
 
-        if self.options.keyname
+        fn x509data(mut self, add: bool) -> Self
         {
-            let keyname = unsafe { bindings::xmlSecTmplKeyInfoAddKeyName(keyinfo, null()) };
+            self.options.x509data = add;
+            self
+        }
 
-            if keyname.is_null() {
-                panic!("Failed to add key name");
-            }
+        fn keyname_text(mut self, name: &str) -> Self
+        {
+            self.options.keyname_text = Some(name.to_owned());
+            self
         }
 
-        if self.options.keyvalue
+        fn x509_certificate(mut self, add: bool) -> Self
         {
-            let keyvalue = unsafe { bindings::xmlSecTmplKeyInfoAddKeyValue(keyinfo) };
+            self.options.x509_certificate = add;
+            self
+        }
 
-            if keyvalue.is_null() {
-                panic!("Failed to add key value");
-            }
+        fn x509_subject_name(mut self, add: bool) -> Self
+        {
+            self.options.x509_subject_name = add;
+            self
         }
 
-        if self.options.x509data
+        fn x509_issuer_serial(mut self, add: bool) -> Self
         {
-            let x509data = unsafe { bindings::xmlSecTmplKeyInfoAddX509Data(keyinfo) };
+            self.options.x509_issuer_serial = add;
+            self
+        }
 
-            if x509data.is_null() {
-                panic!("Failed to add key value");
-            }
+        fn x509_ski(mut self, add: bool) -> Self
+        {
+            self.options.x509_ski = add;
+            self
         }
 
-        unsafe { bindings::xmlAddChild(rootptr, signature) };
+        fn x509_crl(mut self, add: bool) -> Self
+        {
+            self.options.x509_crl = add;
+            self
+        }
 
-        Ok(())
-    }
+        fn x509_digest(mut self, digest: XmlSecSignatureMethod) -> Self
+        {
+            self.options.x509_digest = Some(digest);
+            self
+        }
+
+        fn retrieval_method(mut self, uri: &str, rmtype: Option<&str>) -> Self
+        {
+            self.options.retrieval_method = Some((uri.to_owned(), rmtype.map(|t| t.to_owned())));
+            self
+        }
+
+        fn keyinfo_reference(mut self, uri: &str) -> Self
+        {
+            self.options.keyinfo_reference = Some(uri.to_owned());
+            self
+        }
+
+        fn placement(mut self, placement: TemplatePlacement) -> Self
+        {
+            self.options.placement = placement;
+            self
+        }
+
+    };
 }
+
+impl<'d> TemplateBuilder for XmlDocumentTemplateBuilder<'d>
+{
+    impl_template_options!();
+
+    fn done(self) -> XmlSecResult<()>
+    {
+        let docptr = self.doc.doc_ptr() as *mut bindings::xmlDoc;
+
+        let rootptr = match self.doc.get_root_element()
+        {
+            Some(root) => root.node_ptr() as *mut bindings::xmlNode,
+
+            // a purely enveloping signature may well be the document root itself
+            None if matches!(self.options.placement, TemplatePlacement::FirstChild | TemplatePlacement::LastChild) => {
+                null_mut()
+            }
+
+            None => return Err(XmlSecError::RootNotFound),
+        };
+
+        let target = match &self.options.placement
+        {
+            TemplatePlacement::XPath(search, namespaces) => {
+                let xpath = XmlXPathContext::new(self.doc)
+                    .map_err(|_| XmlSecError::AllocationFailed("XPath context over XML document".into()))?;
+
+                select_single_node(&xpath, search, namespaces, None)?
+            }
+
+            _ => rootptr,
+        };
+
+        self.options.build(docptr, target)
+    }
+}
+
+impl<'n> TemplateBuilder for XmlNodeTemplateBuilder<'n>
+{
+    impl_template_options!();
+
+    fn done(self) -> XmlSecResult<()>
+    {
+        let nodeptr = self.node.node_ptr() as *mut bindings::xmlNode;
+        let docptr  = unsafe { (*nodeptr).doc };
+
+        if docptr.is_null() {
+            return Err(XmlSecError::RootNotFound);
+        }
+
+        let target = match &self.options.placement
+        {
+            TemplatePlacement::XPath(search, namespaces) => {
+                let xpath = XmlXPathContext::from_node(self.node)
+                    .map_err(|_| XmlSecError::AllocationFailed("XPath context over XML node".into()))?;
+
+                select_single_node(&xpath, search, namespaces, Some(self.node))?
+            }
+
+            _ => nodeptr,
+        };
+
+        self.options.build(docptr, target)
+    }
+}
+
+impl TemplateOptions
+{
+    /// Builds the `<dsig:Signature/>` node and inserts it according to the placement option, where `subject` is the
+    /// node child placements are relative to. A null `subject` makes the signature the document root.
+    fn build(self, docptr: *mut bindings::xmlDoc, subject: *mut bindings::xmlNode) -> XmlSecResult<()>
+    {
+        crate::xmlsec::guarantee_xmlsec_init()?;
+
+        self.check_inclusive_namespaces()?;
+        self.check_x509_digest()?;
+
+        let c_ns_prefix = optional_cstring(&self.ns_prefix, "namespace prefix")?;
+        let csigid      = optional_cstring(&self.signature_id, "signature Id")?;
+
+        let signature = unsafe { bindings::xmlSecTmplSignatureCreateNsPref(
+            docptr,
+            self.c14n.to_method(),
+            self.sig.to_method(),
+            optional_ptr(&csigid),
+            optional_ptr(&c_ns_prefix),
+        ) };
+
+        if signature.is_null() {
+            return Err(template_error("creating Signature"));
+        }
+
+        let ids = match self.populate(docptr, signature)
+        {
+            Ok(ids) => ids,
+            Err(e)  => { unsafe { bindings::xmlFreeNode(signature) }; return Err(e); }
+        };
+
+        insert_signature(docptr, subject, signature, &self.placement)?;
+
+        // register Ids only once attached, so that references within this very signing pass can resolve them
+        for (node, id) in ids
+        {
+            if let Err(e) = register_id(docptr, node, id)
+            {
+                // freeing the signature drops the Ids registered so far along with their attributes
+                unsafe {
+                    bindings::xmlUnlinkNode(signature);
+                    bindings::xmlFreeNode(signature);
+                }
+
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fills the freshly created `signature` in, returning the nodes to register Ids for once it is attached.
+    fn populate<'o>(
+        &'o self,
+        docptr:    *mut bindings::xmlDoc,
+        signature: *mut bindings::xmlNode,
+    ) -> XmlSecResult<Vec<(*mut bindings::xmlNode, &'o str)>>
+    {
+        let curi     = optional_cstring(&self.uri, "reference URI")?;
+        let crefid   = optional_cstring(&self.reference_id, "reference Id")?;
+        let creftype = optional_cstring(&self.reftype, "reference type")?;
+        let ckeyid   = optional_cstring(&self.keyinfo_id, "KeyInfo Id")?;
+
+        let reference = unsafe { bindings::xmlSecTmplSignatureAddReference(
+            signature,
+            self.refsig.to_method(),
+            optional_ptr(&crefid),
+            optional_ptr(&curi),
+            optional_ptr(&creftype),
+        ) };
+
+        if reference.is_null() {
+            return Err(template_error("adding Reference"));
+        }
+
+        if self.enveloped
+        {
+            let envelope = unsafe { bindings::xmlSecTmplReferenceAddTransform(reference, bindings::xmlSecTransformEnvelopedGetKlass()) };
+
+            if envelope.is_null() {
+                return Err(template_error("adding enveloped Transform"));
+            }
+        }
+
+        if let Some(prefixes) = &self.c14n_prefixes
+        {
+            let c14nmethod = unsafe { bindings::xmlSecTmplSignatureGetC14NMethodNode(signature) };
+
+            add_inclusive_namespaces(c14nmethod, prefixes)?;
+        }
+
+        if let Some(refc14n) = &self.refc14n
+        {
+            let transform = unsafe { bindings::xmlSecTmplReferenceAddTransform(reference, refc14n.to_method()) };
+
+            if transform.is_null() {
+                return Err(template_error("adding canonicalization Transform"));
+            }
+
+            if let Some(prefixes) = &self.refc14n_prefixes {
+                add_inclusive_namespaces(transform, prefixes)?;
+            }
+        }
+
+        let mut ids = Vec::new();
+
+        for extra in &self.references {
+            add_reference(signature, extra, bindings::xmlSecTmplSignatureAddReference, &mut ids)?;
+        }
+
+        let keyinfo = unsafe { bindings::xmlSecTmplSignatureEnsureKeyInfo(signature, optional_ptr(&ckeyid)) };
+
+        if keyinfo.is_null() {
+            return Err(template_error("adding KeyInfo"));
+        }
+
+        if self.keyname || self.keyname_text.is_some()
+        {
+            let ckeyname = optional_cstring(&self.keyname_text, "key name")?;
+
+            let keyname = unsafe { bindings::xmlSecTmplKeyInfoAddKeyName(keyinfo, optional_ptr(&ckeyname)) };
+
+            if keyname.is_null() {
+                return Err(template_error("adding KeyName"));
+            }
+        }
+
+        if self.keyvalue
+        {
+            let keyvalue = unsafe { bindings::xmlSecTmplKeyInfoAddKeyValue(keyinfo) };
+
+            if keyvalue.is_null() {
+                return Err(template_error("adding KeyValue"));
+            }
+        }
+
+        if self.x509data || self.has_x509data_content()
+        {
+            let x509data = unsafe { bindings::xmlSecTmplKeyInfoAddX509Data(keyinfo) };
+
+            if x509data.is_null() {
+                return Err(template_error("adding X509Data"));
+            }
+
+            self.add_x509data_content(x509data)?;
+        }
+
+        if let Some((uri, rmtype)) = &self.retrieval_method
+        {
+            let crmuri  = cstring(uri, "retrieval method URI")?;
+            let crmtype = optional_cstring(rmtype, "retrieval method type")?;
+
+            let retrieval = unsafe { bindings::xmlSecTmplKeyInfoAddRetrievalMethod(
+                keyinfo,
+                crmuri.as_ptr() as *const c_uchar,
+                optional_ptr(&crmtype)
+            ) };
+
+            if retrieval.is_null() {
+                return Err(template_error("adding RetrievalMethod"));
+            }
+        }
+
+        if let Some(uri) = &self.keyinfo_reference
+        {
+            let reference = add_dsig11_child(keyinfo, NODE_KEYINFO_REFERENCE)?;
+
+            set_attr(reference, unsafe { &bindings::xmlSecAttrURI }, uri)?;
+        }
+
+        for object in &self.objects {
+            add_object(docptr, signature, object, &mut ids)?;
+        }
+
+        if let Some(id) = &self.signature_id {
+            ids.push((signature, id.as_str()));
+        }
+
+        if let Some(id) = &self.signed_info_id
+        {
+            let signedinfo = unsafe { find_dsig_child(signature, &bindings::xmlSecNodeSignedInfo) };
+
+            set_id_attr(signedinfo, id)?;
+            ids.push((signedinfo, id.as_str()));
+        }
+
+        if let Some(id) = &self.signature_value_id
+        {
+            let sigvalue = unsafe { find_dsig_child(signature, &bindings::xmlSecNodeSignatureValue) };
+
+            set_id_attr(sigvalue, id)?;
+            ids.push((sigvalue, id.as_str()));
+        }
+
+        if let Some(id) = &self.keyinfo_id {
+            ids.push((keyinfo, id.as_str()));
+        }
+
+        if let Some(id) = &self.reference_id {
+            ids.push((reference, id.as_str()));
+        }
+
+        check_ids(docptr, &ids)?;
+
+        Ok(ids)
+    }
+
+    fn has_x509data_content(&self) -> bool
+    {
+        self.x509_certificate
+            || self.x509_subject_name
+            || self.x509_issuer_serial
+            || self.x509_ski
+            || self.x509_crl
+            || self.x509_digest.is_some()
+    }
+
+    fn add_x509data_content(&self, x509data: *mut bindings::xmlNode) -> XmlSecResult<()>
+    {
+        let children: [(bool, X509DataChildFn, &str); 5] = [
+            (self.x509_issuer_serial, bindings::xmlSecTmplX509DataAddIssuerSerial, "X509IssuerSerial"),
+            (self.x509_subject_name,  bindings::xmlSecTmplX509DataAddSubjectName,  "X509SubjectName"),
+            (self.x509_ski,           bindings::xmlSecTmplX509DataAddSKI,          "X509SKI"),
+            (self.x509_certificate,   bindings::xmlSecTmplX509DataAddCertificate,  "X509Certificate"),
+            (self.x509_crl,           bindings::xmlSecTmplX509DataAddCRL,          "X509CRL"),
+        ];
+
+        for (add, addfn, name) in children
+        {
+            if !add {
+                continue;
+            }
+
+            let child = unsafe { addfn(x509data) };
+
+            if child.is_null() {
+                return Err(template_error(&format!("adding {}", name)));
+            }
+        }
+
+        if let Some(digest) = &self.x509_digest
+        {
+            let node = add_dsig11_child(x509data, NODE_X509_DIGEST)?;
+            let href = unsafe { (*digest.to_method()).href };
+
+            let attr = unsafe { bindings::xmlSetProp(node, &bindings::xmlSecAttrAlgorithm as *const c_uchar, href) };
+
+            if attr.is_null() {
+                return Err(template_error("setting X509Digest Algorithm"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `<dsig11:X509Digest/>` names the digest of the certificate, which a signature method is not.
+    fn check_x509_digest(&self) -> XmlSecResult<()>
+    {
+        match &self.x509_digest
+        {
+            Some(digest) if !digest.is_digest() => Err(XmlSecError::InvalidInput(
+                format!("X509Digest algorithm {:?} is not a digest method", digest)
+            )),
+
+            _ => Ok(()),
+        }
+    }
+
+    /// `<ec:InclusiveNamespaces/>` is only defined for exclusive canonicalization, so refuse to build a template
+    /// xmlsec would reject later on while signing.
+    fn check_inclusive_namespaces(&self) -> XmlSecResult<()>
+    {
+        if self.c14n_prefixes.is_some() && !self.c14n.is_exclusive() {
+            return Err(XmlSecError::InvalidInput(
+                "inclusive namespaces require an exclusive canonicalization method".into()
+            ));
+        }
+
+        if self.refc14n_prefixes.is_some() && !matches!(&self.refc14n, Some(m) if m.is_exclusive()) {
+            return Err(XmlSecError::InvalidInput(
+                "reference inclusive namespaces require an exclusive reference canonicalization method".into()
+            ));
+        }
+
+        let manifests = self.objects.iter()
+            .filter_map(|o| match &o.content {
+                ObjectContent::Manifest(_, references) => Some(references.iter()),
+                _                                      => None,
+            })
+            .flatten();
+
+        for reference in self.references.iter().chain(manifests)
+        {
+            if reference.c14n_prefixes.is_some() && !matches!(&reference.c14n, Some(m) if m.is_exclusive()) {
+                return Err(XmlSecError::InvalidInput(
+                    "reference inclusive namespaces require an exclusive reference canonicalization method".into()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Adds `reference` below `parent` through `addfn`, being either the `<dsig:SignedInfo/>` or `<dsig:Manifest/>`
+/// flavour, with its transforms in enveloped, canonicalization, base64 order.
+fn add_reference<'o>(
+    parent:    *mut bindings::xmlNode,
+    reference: &'o TemplateReference,
+    addfn:     ReferenceAddFn,
+    ids:       &mut Vec<(*mut bindings::xmlNode, &'o str)>,
+) -> XmlSecResult<*mut bindings::xmlNode>
+{
+    let curi     = optional_cstring(&reference.uri, "reference URI")?;
+    let cid      = optional_cstring(&reference.id, "reference Id")?;
+    let creftype = optional_cstring(&reference.reftype, "reference type")?;
+
+    let node = unsafe { addfn(
+        parent,
+        reference.digest.to_method(),
+        optional_ptr(&cid),
+        optional_ptr(&curi),
+        optional_ptr(&creftype),
+    ) };
+
+    if node.is_null() {
+        return Err(template_error("adding Reference"));
+    }
+
+    let add_transform = |transform: bindings::xmlSecTransformId| {
+        let added = unsafe { bindings::xmlSecTmplReferenceAddTransform(node, transform) };
+
+        if added.is_null() {
+            return Err(template_error("adding Transform"));
+        }
+
+        Ok(added)
+    };
+
+    if reference.enveloped {
+        add_transform(unsafe { bindings::xmlSecTransformEnvelopedGetKlass() })?;
+    }
+
+    if let Some(c14n) = &reference.c14n
+    {
+        let transform = add_transform(c14n.to_method())?;
+
+        if let Some(prefixes) = &reference.c14n_prefixes {
+            add_inclusive_namespaces(transform, prefixes)?;
+        }
+    }
+
+    if reference.base64 {
+        add_transform(unsafe { bindings::xmlSecTransformBase64GetKlass() })?;
+    }
+
+    for transform in &reference.custom {
+        add_transform(transform.to_method())?;
+    }
+
+    if let Some(id) = &reference.id {
+        ids.push((node, id.as_str()));
+    }
+
+    Ok(node)
+}
+
+/// Appends `object` to `signature` along with its content.
+fn add_object<'o>(
+    docptr:    *mut bindings::xmlDoc,
+    signature: *mut bindings::xmlNode,
+    object:    &'o TemplateObject,
+    ids:       &mut Vec<(*mut bindings::xmlNode, &'o str)>,
+) -> XmlSecResult<()>
+{
+    let encoding = match (&object.encoding, &object.content)
+    {
+        (Some(encoding), _)              => Some(encoding.clone()),
+        (None, ObjectContent::Binary(_)) => Some(unsafe { cstr_constant(&bindings::xmlSecHrefBase64) }),
+        (None, _)                        => None,
+    };
+
+    let cid       = optional_cstring(&object.id, "object Id")?;
+    let cmimetype = optional_cstring(&object.mime_type, "object MIME type")?;
+    let cencoding = optional_cstring(&encoding, "object encoding")?;
+
+    let node = unsafe { bindings::xmlSecTmplSignatureAddObject(
+        signature,
+        optional_ptr(&cid),
+        optional_ptr(&cmimetype),
+        optional_ptr(&cencoding),
+    ) };
+
+    if node.is_null() {
+        return Err(template_error("adding Object"));
+    }
+
+    if let Some(id) = &object.id {
+        ids.push((node, id.as_str()));
+    }
+
+    match &object.content
+    {
+        ObjectContent::Xml(content) => {
+            copy_node_into(docptr, node, content)?;
+        }
+
+        ObjectContent::Binary(bytes) => unsafe {
+            let buffer = bindings::xmlSecBufferCreate(bytes.len() as bindings::xmlSecSize);
+
+            if buffer.is_null() {
+                return Err(XmlSecError::AllocationFailed("object content buffer".into()));
+            }
+
+            let mut rc = bindings::xmlSecBufferSetData(buffer, bytes.as_ptr(), bytes.len() as bindings::xmlSecSize);
+
+            if rc >= 0 {
+                rc = bindings::xmlSecBufferBase64NodeContentWrite(buffer, node, bindings::xmlSecBase64GetDefaultLineSize());
+            }
+
+            bindings::xmlSecBufferDestroy(buffer);
+
+            if rc < 0 {
+                return Err(template_error("base64 encoding Object content"));
+            }
+        },
+
+        ObjectContent::SignatureProperties(id, properties) => {
+            let cpropsid = optional_cstring(id, "signature properties Id")?;
+
+            let props = unsafe { bindings::xmlSecTmplObjectAddSignProperties(node, optional_ptr(&cpropsid), null()) };
+
+            if props.is_null() {
+                return Err(template_error("adding SignatureProperties"));
+            }
+
+            if let Some(id) = id {
+                ids.push((props, id.as_str()));
+            }
+
+            for (target, content) in properties
+            {
+                // xmlsec1 1.2 has no templating for single properties, hence these get added by hand
+                let property = unsafe { bindings::xmlSecAddChild(
+                    props,
+                    NODE_SIGNATURE_PROPERTY.as_ptr(),
+                    &bindings::xmlSecDSigNs as *const c_uchar,
+                ) };
+
+                if property.is_null() {
+                    return Err(template_error("adding SignatureProperty"));
+                }
+
+                set_attr(property, unsafe { &bindings::xmlSecAttrTarget }, target)?;
+                copy_node_into(docptr, property, content)?;
+            }
+        }
+
+        ObjectContent::Manifest(id, references) => {
+            let cmanifestid = optional_cstring(id, "manifest Id")?;
+
+            let manifest = unsafe { bindings::xmlSecTmplObjectAddManifest(node, optional_ptr(&cmanifestid)) };
+
+            if manifest.is_null() {
+                return Err(template_error("adding Manifest"));
+            }
+
+            if let Some(id) = id {
+                ids.push((manifest, id.as_str()));
+            }
+
+            for reference in references {
+                add_reference(manifest, reference, bindings::xmlSecTmplManifestAddReference, ids)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Deep copies `content` into `docptr`, appending it to `parent`.
+fn copy_node_into(docptr: *mut bindings::xmlDoc, parent: *mut bindings::xmlNode, content: &XmlNode) -> XmlSecResult<()>
+{
+    unsafe {
+        let copy = bindings::xmlDocCopyNode(content.node_ptr() as *mut bindings::xmlNode, docptr, 1);
+
+        if copy.is_null() {
+            return Err(template_error("copying Object content"));
+        }
+
+        if bindings::xmlAddChild(parent, copy).is_null()
+        {
+            bindings::xmlFreeNode(copy);
+
+            return Err(template_error("appending Object content"));
+        }
+    }
+
+    Ok(())
+}
+
+fn add_inclusive_namespaces(transform: *mut bindings::xmlNode, prefixes: &str) -> XmlSecResult<()>
+{
+    let cprefixes = cstring(prefixes, "inclusive namespace prefixes")?;
+
+    let rc = unsafe { bindings::xmlSecTmplTransformAddC14NInclNamespaces(
+        transform,
+        cprefixes.as_ptr() as *const c_uchar
+    ) };
+
+    if rc < 0 {
+        return Err(template_error(&format!("adding InclusiveNamespaces '{}'", prefixes)));
+    }
+
+    Ok(())
+}
+
+fn insert_signature(
+    docptr:    *mut bindings::xmlDoc,
+    subject:   *mut bindings::xmlNode,
+    signature: *mut bindings::xmlNode,
+    placement: &TemplatePlacement,
+) -> XmlSecResult<()>
+{
+    if subject.is_null()
+    {
+        unsafe { bindings::xmlDocSetRootElement(docptr, signature) };
+
+        return Ok(());
+    }
+
+    let inserted = match placement
+    {
+        TemplatePlacement::LastChild | TemplatePlacement::XPath(..) => unsafe {
+            bindings::xmlAddChild(subject, signature)
+        },
+
+        TemplatePlacement::FirstChild => unsafe {
+            let first = (*subject).children;
+
+            if first.is_null() {
+                bindings::xmlAddChild(subject, signature)
+            } else {
+                bindings::xmlAddPrevSibling(first, signature)
+            }
+        },
+
+        TemplatePlacement::Before(sibling) => match sibling_within(docptr, sibling)
+        {
+            Ok(sibling) => unsafe { bindings::xmlAddPrevSibling(sibling, signature) },
+            Err(e)      => { unsafe { bindings::xmlFreeNode(signature) }; return Err(e); }
+        },
+
+        TemplatePlacement::After(sibling) => match sibling_within(docptr, sibling)
+        {
+            Ok(sibling) => unsafe { bindings::xmlAddNextSibling(sibling, signature) },
+            Err(e)      => { unsafe { bindings::xmlFreeNode(signature) }; return Err(e); }
+        },
+    };
+
+    if inserted.is_null()
+    {
+        unsafe { bindings::xmlFreeNode(signature) };
+
+        return Err(template_error("inserting Signature into the document"));
+    }
+
+    Ok(())
+}
+
+/// Ensures a sibling anchor lives inside an element of the document being templated, so that inserting next to it
+/// can neither add a second root element nor graft the signature onto a foreign document.
+fn sibling_within(docptr: *mut bindings::xmlDoc, sibling: &XmlNode) -> XmlSecResult<*mut bindings::xmlNode>
+{
+    let siblingptr = sibling.node_ptr() as *mut bindings::xmlNode;
+
+    unsafe {
+        if (*siblingptr).doc != docptr {
+            return Err(XmlSecError::InvalidInput("signature placement node belongs to another document".into()));
+        }
+
+        let parent = (*siblingptr).parent;
+
+        if parent.is_null() || (*parent).type_ != bindings::xmlElementType_XML_ELEMENT_NODE {
+            return Err(XmlSecError::InvalidInput("signature placement node must have a parent element".into()));
+        }
+    }
+
+    Ok(siblingptr)
+}
+
+fn select_single_node(
+    xpath:      &XmlXPathContext,
+    search:     &str,
+    namespaces: &[(String, String)],
+    relative:   Option<&XmlNode>,
+) -> XmlSecResult<*mut bindings::xmlNode>
+{
+    register_namespaces(xpath, namespaces)?;
+
+    let result = match relative
+    {
+        Some(node) => xpath.node_evaluate(search, node),
+        None       => xpath.evaluate(search),
+    };
+
+    let nodes = match result
+    {
+        Ok(r)  => r.get_nodes_as_vec(),
+        Err(_) => { return Err(XmlSecError::InvalidInput(format!("XPath expression '{}'", search))); }
+    };
+
+    match nodes.as_slice()
+    {
+        [node] if node.is_element_node() => Ok(node.node_ptr() as *mut bindings::xmlNode),
+        []                               => Err(XmlSecError::NodeNotFound),
+        _                                => Err(XmlSecError::InvalidInput(
+            format!("XPath '{}' must select exactly one element to place the signature in", search)
+        )),
+    }
+}
+
+fn optional_cstring(value: &Option<String>, what: &str) -> XmlSecResult<Option<CString>>
+{
+    value.as_ref()
+        .map(|v| cstring(v, what))
+        .transpose()
+}
+
+fn template_error(step: &str) -> XmlSecError
+{
+    XmlSecError::TemplateError(step.to_owned())
+}
+
+fn optional_ptr(value: &Option<CString>) -> *const c_uchar
+{
+    value.as_ref()
+        .map(|v| v.as_ptr() as *const c_uchar)
+        .unwrap_or(null())
+}
+
+/// # Safety
+///
+/// `constant` must be one of the null terminated xmlsec string constants.
+unsafe fn cstr_constant(constant: &[c_uchar; 0]) -> String
+{
+    CStr::from_ptr(constant as *const c_uchar as *const c_char)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// # Safety
+///
+/// `name` must be one of the null terminated xmlsec node name constants.
+unsafe fn find_dsig_child(signature: *mut bindings::xmlNode, name: &[c_uchar; 0]) -> *mut bindings::xmlNode
+{
+    bindings::xmlSecFindChild(
+        signature,
+        name                   as *const c_uchar,
+        &bindings::xmlSecDSigNs as *const c_uchar,
+    )
+}
+
+fn set_id_attr(node: *mut bindings::xmlNode, id: &str) -> XmlSecResult<()>
+{
+    set_attr(node, unsafe { &bindings::xmlSecAttrId }, id)
+}
+
+fn set_attr(node: *mut bindings::xmlNode, name: &[c_uchar; 0], value: &str) -> XmlSecResult<()>
+{
+    let cvalue = cstring(value, "attribute value")?;
+
+    let attr = unsafe { bindings::xmlSetProp(
+        node,
+        name as *const c_uchar,
+        cvalue.as_ptr() as *const c_uchar
+    ) };
+
+    if attr.is_null() {
+        return Err(template_error("setting attribute"));
+    }
+
+    Ok(())
+}
+
+/// xmlsec1 1.2 has no templating support for XML Signature 1.1 nodes, hence these get added by hand.
+fn add_dsig11_child(parent: *mut bindings::xmlNode, name: &[u8]) -> XmlSecResult<*mut bindings::xmlNode>
+{
+    let child = unsafe { bindings::xmlSecAddChild(
+        parent,
+        name.as_ptr(),
+        DSIG11_NS.as_ptr(),
+    ) };
+
+    if child.is_null() {
+        return Err(template_error("adding XML Signature 1.1 node"));
+    }
+
+    Ok(child)
+}
+
+/// Ensures none of the Ids to be set collides with an Id already registered in the document, nor with each other.
+fn check_ids(docptr: *mut bindings::xmlDoc, ids: &[(*mut bindings::xmlNode, &str)]) -> XmlSecResult<()>
+{
+    for (i, (_, id)) in ids.iter().enumerate()
+    {
+        let cid      = cstring(id, "Id")?;
+        let existing = unsafe { bindings::xmlGetID(docptr, cid.as_ptr() as *const c_uchar) };
+
+        if !existing.is_null() || ids[..i].iter().any(|(_, other)| other == id) {
+            return Err(XmlSecError::InvalidInput(format!("duplicate ID attribute {}", id)));
+        }
+    }
+
+    Ok(())
+}
+
+fn register_id(docptr: *mut bindings::xmlDoc, node: *mut bindings::xmlNode, id: &str) -> XmlSecResult<()>
+{
+    let cid = cstring(id, "Id")?;
+
+    let attr = unsafe { bindings::xmlHasProp(node, &bindings::xmlSecAttrId as *const c_uchar) };
+
+    if attr.is_null() {
+        return Err(template_error(&format!("Id attribute {} missing from its node", id)));
+    }
+
+    // libxml refuses an Id already registered within the document
+    let added = unsafe { bindings::xmlAddID(null_mut(), docptr, cid.as_ptr() as *const c_uchar, attr) };
+
+    if added.is_null() {
+        return Err(template_error(&format!("Id {} already registered in the document", id)));
+    }
+
+    Ok(())
+}
+
@@ -3,9 +3,43 @@
 //!
 use crate::bindings;
 
+use crate::XmlNode;
+use crate::XmlDocument;
+
+use crate::XmlSecKey;
+use crate::XmlSecError;
+use crate::XmlSecResult;
+use crate::XmlSecSignatureMethod;
+
+use crate::error::cstring;
+
+use crate::lazy_static;
+
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::os::raw::c_int;
+use std::os::raw::c_uchar;
+use std::os::raw::c_void;
+use std::mem::forget;
+use std::mem::size_of;
+use std::panic::catch_unwind;
+use std::panic::AssertUnwindSafe;
+use std::ptr::null;
+use std::ptr::null_mut;
+use std::slice;
+use std::sync::Mutex;
+
+
+lazy_static! {
+    // guards the lookup and registration of custom transforms within the global xmlsec transforms list
+    static ref REGISTRATION: Mutex<()> = Mutex::new(());
+}
+
 
 /// Supported canonical methods as specified by the XML standard.
 #[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum XmlSecCanonicalizationMethod
 {
     InclusiveC14N,
@@ -19,6 +53,23 @@ pub enum XmlSecCanonicalizationMethod
 
 impl XmlSecCanonicalizationMethod
 {
+    const ALL: [Self; 6] = [
+        Self::InclusiveC14N,
+        Self::InclusiveC14NWithComments,
+        Self::InclusiveC14N11,
+        Self::InclusiveC14N11WithComments,
+        Self::ExclusiveC14N,
+        Self::ExclusiveC14NWithComments,
+    ];
+
+    /// Looks up the method identified by an `Algorithm` URI, if supported.
+    pub fn from_href(href: &str) -> Option<Self>
+    {
+        Self::ALL.iter()
+            .find(|m| unsafe { transform_href(m.to_method()) } == Some(href))
+            .copied()
+    }
+
     /// Returns the resource pointer for the corresponding canonicalization ressource
     pub fn to_method(&self) -> bindings::xmlSecTransformId
     {
@@ -32,4 +83,823 @@ impl XmlSecCanonicalizationMethod
             Self::ExclusiveC14NWithComments   => unsafe { bindings::xmlSecTransformExclC14NWithCommentsGetKlass() },
         }
     }
+
+    /// Whether this is one of the exclusive canonicalization methods, which are the only ones accepting an
+    /// `<ec:InclusiveNamespaces PrefixList="..."/>` parameter.
+    pub fn is_exclusive(&self) -> bool
+    {
+        matches!(self, Self::ExclusiveC14N | Self::ExclusiveC14NWithComments)
+    }
+
+    fn with_comments(&self) -> bool
+    {
+        matches!(
+            self,
+            Self::InclusiveC14NWithComments | Self::InclusiveC14N11WithComments | Self::ExclusiveC14NWithComments
+        )
+    }
+}
+
+
+/// Anything naming a transform klass, so as to enable it on a [`XmlSecTransformContext`][ctx] or a
+/// [`XmlSecSignatureContext`][sigctx]. Transforms without a dedicated type, such as the enveloped signature one, are
+/// given by their raw `xmlSecTransformId`.
+///
+/// [ctx]: struct.XmlSecTransformContext.html
+/// [sigctx]: ../xmldsig/struct.XmlSecSignatureContext.html
+pub trait XmlSecTransformMethod
+{
+    /// Returns the resource pointer of the transform klass
+    fn transform_id(&self) -> bindings::xmlSecTransformId;
+}
+
+
+impl XmlSecTransformMethod for XmlSecCanonicalizationMethod
+{
+    fn transform_id(&self) -> bindings::xmlSecTransformId
+    {
+        self.to_method()
+    }
+}
+
+
+impl XmlSecTransformMethod for XmlSecSignatureMethod
+{
+    fn transform_id(&self) -> bindings::xmlSecTransformId
+    {
+        self.to_method()
+    }
+}
+
+
+impl XmlSecTransformMethod for XmlSecCustomTransformId
+{
+    fn transform_id(&self) -> bindings::xmlSecTransformId
+    {
+        self.klass
+    }
+}
+
+
+impl XmlSecTransformMethod for bindings::xmlSecTransformId
+{
+    fn transform_id(&self) -> bindings::xmlSecTransformId
+    {
+        *self
+    }
+}
+
+
+/// Canonicalization of whole documents or subtrees thereof, outside of any signature.
+pub trait XmlSecCanonicalize
+{
+    /// Returns the canonical form of `self` according to `method`. Exclusive methods take an optional whitespace
+    /// separated `PrefixList` of namespaces to be treated inclusively.
+    fn canonicalize(&self, method: XmlSecCanonicalizationMethod, inclusive_prefixes: Option<&str>) -> XmlSecResult<Vec<u8>>;
+}
+
+
+impl XmlSecCanonicalize for XmlDocument
+{
+    fn canonicalize(&self, method: XmlSecCanonicalizationMethod, inclusive_prefixes: Option<&str>) -> XmlSecResult<Vec<u8>>
+    {
+        crate::xmlsec::guarantee_xmlsec_init()?;
+
+        let docptr = self.doc_ptr() as *mut bindings::xmlDoc;
+        let nodes  = unsafe { bindings::xmlSecNodeSetGetChildren(docptr, null_mut(), method.with_comments() as i32, 0) };
+
+        canonicalize_node_set(nodes, &method, inclusive_prefixes)
+    }
+}
+
+
+impl XmlSecCanonicalize for XmlNode
+{
+    fn canonicalize(&self, method: XmlSecCanonicalizationMethod, inclusive_prefixes: Option<&str>) -> XmlSecResult<Vec<u8>>
+    {
+        crate::xmlsec::guarantee_xmlsec_init()?;
+
+        let nodeptr = self.node_ptr() as *mut bindings::xmlNode;
+        let docptr  = unsafe { (*nodeptr).doc };
+
+        if docptr.is_null() {
+            return Err(XmlSecError::InvalidInput("node to be canonicalized does not belong to any document".into()));
+        }
+
+        let nodes = unsafe { bindings::xmlSecNodeSetGetChildren(docptr, nodeptr, method.with_comments() as i32, 0) };
+
+        canonicalize_node_set(nodes, &method, inclusive_prefixes)
+    }
+}
+
+
+/// Canonicalizes exactly the nodes of `set` within `doc`, as selected by an XPath expression, instead of whole
+/// subtrees. Takes ownership of the libxml2 node set.
+pub(crate) fn canonicalize_selection(
+    doc:                &XmlDocument,
+    set:                *mut bindings::xmlNodeSet,
+    method:             &XmlSecCanonicalizationMethod,
+    inclusive_prefixes: Option<&str>,
+) -> XmlSecResult<Vec<u8>>
+{
+    if let Err(e) = crate::xmlsec::guarantee_xmlsec_init()
+    {
+        unsafe { bindings::xmlXPathFreeNodeSet(set) };
+        return Err(e);
+    }
+
+    let docptr = doc.doc_ptr() as *mut bindings::xmlDoc;
+    let nodes  = unsafe { bindings::xmlSecNodeSetCreate(docptr, set, bindings::xmlSecNodeSetType_xmlSecNodeSetNormal) };
+
+    if nodes.is_null() {
+        unsafe { bindings::xmlXPathFreeNodeSet(set) };
+    }
+
+    canonicalize_node_set(nodes, method, inclusive_prefixes)
+}
+
+
+/// Runs `nodes` through a transform context holding only the canonicalization `method`, taking ownership of the node
+/// set.
+fn canonicalize_node_set(
+    nodes:              *mut bindings::xmlSecNodeSet,
+    method:             &XmlSecCanonicalizationMethod,
+    inclusive_prefixes: Option<&str>,
+) -> XmlSecResult<Vec<u8>>
+{
+    if nodes.is_null() {
+        return Err(XmlSecError::AllocationFailed("node set to be canonicalized".into()));
+    }
+
+    let mut ctx = match XmlSecTransformContext::new()
+    {
+        Ok(ctx) => ctx,
+        Err(e)  => { unsafe { bindings::xmlSecNodeSetDestroy(nodes) }; return Err(e); }
+    };
+
+    let canonical = ctx.append_canonicalization(*method, inclusive_prefixes)
+        .and_then(|_| unsafe { ctx.xml_execute_raw(nodes) })
+        .map(|_| ctx.result().unwrap_or_default());
+
+    unsafe { bindings::xmlSecNodeSetDestroy(nodes) };
+
+    canonical
+}
+
+
+/// Chain of transforms data can be pushed through, either as bytes or as XML node set. Wraps `xmlSecTransformCtx`,
+/// which executes only once: build a new context for each input.
+pub struct XmlSecTransformContext
+{
+    ctx:     *mut bindings::xmlSecTransformCtx,
+    scratch: Vec<*mut bindings::xmlDoc>,
+}
+
+
+impl XmlSecTransformContext
+{
+    /// Builds an empty context, ensuring xmlsec is initialized. An empty context passes its input through unaltered.
+    pub fn new() -> XmlSecResult<Self>
+    {
+        crate::xmlsec::guarantee_xmlsec_init()?;
+
+        let ctx = unsafe { bindings::xmlSecTransformCtxCreate() };
+
+        if ctx.is_null() {
+            return Err(XmlSecError::AllocationFailed("transform context".into()));
+        }
+
+        Ok(Self {ctx, scratch: Vec::new()})
+    }
+
+    /// Restricts the transforms accepted by this context to those enabled, be it when appending them or when xmlsec
+    /// reads them from XML. As long as none is enabled, every registered transform is accepted, as xmlsec does.
+    pub fn enable_transform<T: XmlSecTransformMethod>(&mut self, transform: T) -> XmlSecResult<()>
+    {
+        let rc = unsafe { bindings::xmlSecPtrListAdd(
+            &mut (*self.ctx).enabledTransforms,
+            transform.transform_id() as *mut c_void,
+        ) };
+
+        if rc < 0 {
+            return Err(XmlSecError::AllocationFailed("enabled transforms entry".into()));
+        }
+
+        Ok(())
+    }
+
+    /// Appends a canonicalization transform. Exclusive methods take an optional whitespace separated `PrefixList` of
+    /// namespaces to be treated inclusively.
+    pub fn append_canonicalization(
+        &mut self,
+        method:             XmlSecCanonicalizationMethod,
+        inclusive_prefixes: Option<&str>,
+    ) -> XmlSecResult<()>
+    {
+        self.check_enabled(method.to_method())?;
+
+        let transform = match inclusive_prefixes
+        {
+            Some(_) if !method.is_exclusive() => {
+                return Err(XmlSecError::InvalidInput(
+                    "inclusive namespaces require an exclusive canonicalization method".into()
+                ));
+            }
+
+            Some(prefixes) => {
+                let cprefixes = cstring(prefixes, "inclusive namespace prefixes")?;
+
+                unsafe { read_c14n_transform(self.ctx, &mut self.scratch, &method, &cprefixes) }
+            }
+
+            None => unsafe { bindings::xmlSecTransformCtxCreateAndAppend(self.ctx, method.to_method()) },
+        };
+
+        if transform.is_null() {
+            return Err(XmlSecError::TransformError("appending canonicalization transform".into()));
+        }
+
+        Ok(())
+    }
+
+    /// Appends a base64 decoding transform.
+    pub fn append_base64_decode(&mut self) -> XmlSecResult<()>
+    {
+        self.append_base64(bindings::xmlSecTransformOperation_xmlSecTransformOperationDecode)
+    }
+
+    /// Appends a base64 encoding transform, wrapping lines as xmlsec does by default (see
+    /// `xmlSecBase64SetDefaultLineSize`).
+    pub fn append_base64_encode(&mut self) -> XmlSecResult<()>
+    {
+        self.append_base64(bindings::xmlSecTransformOperation_xmlSecTransformOperationEncode)
+    }
+
+    /// Appends a digest transform, e.g. [`XmlSecSignatureMethod::Sha256`][sig], whose output is the digest of its
+    /// input.
+    ///
+    /// [sig]: ./crypto/openssl/enum.XmlSecSignatureMethod.html
+    pub fn append_digest(&mut self, method: XmlSecSignatureMethod) -> XmlSecResult<()>
+    {
+        self.append_with_operation(method.to_method(), bindings::xmlSecTransformUsageDigestMethod)?;
+
+        Ok(())
+    }
+
+    /// Appends a signature transform, e.g. [`XmlSecSignatureMethod::RsaSha256`][sig], whose output is the signature of
+    /// its input made with `key`.
+    ///
+    /// [sig]: ./crypto/openssl/enum.XmlSecSignatureMethod.html
+    pub fn append_signature(&mut self, method: XmlSecSignatureMethod, key: &XmlSecKey) -> XmlSecResult<()>
+    {
+        let transform = self.append_with_operation(method.to_method(), bindings::xmlSecTransformUsageSignatureMethod)?;
+
+        // the transform keeps its own copy of the key material
+        if unsafe { bindings::xmlSecTransformSetKey(transform, key.as_ptr()) } < 0 {
+            return Err(XmlSecError::TransformError("setting key on signature transform".into()));
+        }
+
+        Ok(())
+    }
+
+    /// Appends a transform implemented in Rust, as obtained from [`XmlSecCustomTransformId::register`][register].
+    ///
+    /// [register]: struct.XmlSecCustomTransformId.html#method.register
+    pub fn append_custom(&mut self, transform: XmlSecCustomTransformId) -> XmlSecResult<()>
+    {
+        self.check_enabled(transform.to_method())?;
+
+        let appended = unsafe { bindings::xmlSecTransformCtxCreateAndAppend(self.ctx, transform.to_method()) };
+
+        if appended.is_null() {
+            return Err(XmlSecError::TransformError(format!("appending custom transform {}", transform.href())));
+        }
+
+        Ok(())
+    }
+
+    /// Pushes `data` through the chain of transforms, read the output with [`result`][result].
+    ///
+    /// [result]: struct.XmlSecTransformContext.html#method.result
+    pub fn binary_execute(&mut self, data: &[u8]) -> XmlSecResult<()>
+    {
+        let rc = unsafe { bindings::xmlSecTransformCtxBinaryExecute(
+            self.ctx,
+            data.as_ptr(),
+            data.len() as bindings::xmlSecSize,
+        ) };
+
+        if rc < 0 {
+            return Err(XmlSecError::TransformError("executing over binary data".into()));
+        }
+
+        Ok(())
+    }
+
+    /// Pushes the whole document through the chain of transforms. A chain starting with a binary transform gets
+    /// the document inclusively canonicalized first.
+    pub fn xml_execute_document(&mut self, doc: &XmlDocument) -> XmlSecResult<()>
+    {
+        let docptr = doc.doc_ptr() as *mut bindings::xmlDoc;
+
+        unsafe { self.xml_execute_owned(bindings::xmlSecNodeSetGetChildren(docptr, null_mut(), 1, 0)) }
+    }
+
+    /// Pushes the subtree of `node` through the chain of transforms. A chain starting with a binary transform gets
+    /// the subtree inclusively canonicalized first.
+    pub fn xml_execute_node(&mut self, node: &XmlNode) -> XmlSecResult<()>
+    {
+        let nodeptr = node.node_ptr() as *mut bindings::xmlNode;
+        let docptr  = unsafe { (*nodeptr).doc };
+
+        if docptr.is_null() {
+            return Err(XmlSecError::InvalidInput("node to be transformed does not belong to any document".into()));
+        }
+
+        unsafe { self.xml_execute_owned(bindings::xmlSecNodeSetGetChildren(docptr, nodeptr, 1, 0)) }
+    }
+
+    /// Output of the last transform, once executed.
+    pub fn result(&self) -> Option<Vec<u8>>
+    {
+        unsafe {
+            let result = (*self.ctx).result;
+
+            if result.is_null() {
+                return None;
+            }
+
+            let data = bindings::xmlSecBufferGetData(result);
+
+            if data.is_null() {
+                return Some(Vec::new());
+            }
+
+            Some(slice::from_raw_parts(data, bindings::xmlSecBufferGetSize(result) as usize).to_vec())
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `nodes` must be a valid node set, which remains owned by the caller.
+    pub unsafe fn xml_execute_raw(&mut self, nodes: *mut bindings::xmlSecNodeSet) -> XmlSecResult<()>
+    {
+        if bindings::xmlSecTransformCtxXmlExecute(self.ctx, nodes) < 0 {
+            return Err(XmlSecError::TransformError("executing over XML nodes".into()));
+        }
+
+        Ok(())
+    }
+
+    /// # Safety
+    ///
+    /// Returns a raw pointer to the underlying xmlsec transform context. Beware that it is still managed by this
+    /// wrapping object and will be deallocated once `self` gets dropped.
+    pub unsafe fn as_ptr(&self) -> *mut bindings::xmlSecTransformCtx
+    {
+        self.ctx
+    }
+
+    /// # Safety
+    ///
+    /// Returns a raw pointer to the underlying xmlsec transform context. Beware that it will be forgotten by this
+    /// wrapping object and *must* be deallocated manually by the callee.
+    pub unsafe fn into_ptr(self) -> *mut bindings::xmlSecTransformCtx
+    {
+        let ctx = self.ctx;
+
+        forget(self);
+
+        ctx
+    }
+}
+
+
+impl XmlSecTransformContext
+{
+    fn append_base64(&mut self, operation: bindings::xmlSecTransformOperation) -> XmlSecResult<()>
+    {
+        self.check_enabled(unsafe { bindings::xmlSecTransformBase64GetKlass() })?;
+
+        let transform = unsafe { bindings::xmlSecTransformCtxCreateAndAppend(
+            self.ctx,
+            bindings::xmlSecTransformBase64GetKlass(),
+        ) };
+
+        if transform.is_null() {
+            return Err(XmlSecError::TransformError("appending base64 transform".into()));
+        }
+
+        unsafe { (*transform).operation = operation };
+
+        Ok(())
+    }
+
+    /// Digests and signatures compute their output when set to sign, and would only compare against it otherwise.
+    fn append_with_operation(
+        &mut self,
+        id:    bindings::xmlSecTransformId,
+        usage: bindings::xmlSecTransformUsage,
+    ) -> XmlSecResult<*mut bindings::xmlSecTransform>
+    {
+        let href = unsafe { transform_href(id) }.unwrap_or_default();
+
+        if unsafe { (*id).usage } & usage == 0 {
+            return Err(XmlSecError::InvalidInput(format!("transform {} does not fit the intended usage", href)));
+        }
+
+        self.check_enabled(id)?;
+
+        let transform = unsafe { bindings::xmlSecTransformCtxCreateAndAppend(self.ctx, id) };
+
+        if transform.is_null() {
+            return Err(XmlSecError::TransformError(format!("appending transform {}", href)));
+        }
+
+        unsafe { (*transform).operation = bindings::xmlSecTransformOperation_xmlSecTransformOperationSign };
+
+        Ok(transform)
+    }
+
+    /// Refuses transforms left out of a non empty list of [enabled][enable] ones, which xmlsec only checks itself when
+    /// reading transforms from XML.
+    ///
+    /// [enable]: struct.XmlSecTransformContext.html#method.enable_transform
+    fn check_enabled(&self, id: bindings::xmlSecTransformId) -> XmlSecResult<()>
+    {
+        let enabled = unsafe { &mut (*self.ctx).enabledTransforms as *mut bindings::xmlSecPtrList };
+
+        let refused = unsafe {
+            bindings::xmlSecPtrListGetSize(enabled) > 0 && bindings::xmlSecTransformIdListFind(enabled, id) != 1
+        };
+
+        if refused {
+            return Err(XmlSecError::TransformError(
+                format!("transform {} is not enabled", unsafe { transform_href(id) }.unwrap_or_default())
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// # Safety
+    ///
+    /// Takes ownership of `nodes`, which may be null on failure to build it.
+    unsafe fn xml_execute_owned(&mut self, nodes: *mut bindings::xmlSecNodeSet) -> XmlSecResult<()>
+    {
+        if nodes.is_null() {
+            return Err(XmlSecError::AllocationFailed("node set to be transformed".into()));
+        }
+
+        let result = self.xml_execute_raw(nodes);
+
+        bindings::xmlSecNodeSetDestroy(nodes);
+
+        result
+    }
+}
+
+
+impl Drop for XmlSecTransformContext
+{
+    fn drop(&mut self)
+    {
+        unsafe { bindings::xmlSecTransformCtxDestroy(self.ctx) };
+
+        // only freed once the transforms pointing into them are gone
+        for doc in self.scratch.drain(..) {
+            unsafe { bindings::xmlFreeDoc(doc) };
+        }
+    }
+}
+
+
+/// Places within a signature where a [`XmlSecCustomTransform`][custom] may be used.
+///
+/// [custom]: trait.XmlSecCustomTransform.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XmlSecTransformUsage
+{
+    /// `<dsig:Transform/>` of a reference
+    DSigTransform,
+
+    /// `<dsig:CanonicalizationMethod/>` of `<dsig:SignedInfo/>`
+    C14NMethod,
+}
+
+
+impl XmlSecTransformUsage
+{
+    fn to_raw(self) -> bindings::xmlSecTransformUsage
+    {
+        match self
+        {
+            Self::DSigTransform => bindings::xmlSecTransformUsageDSigTransform,
+            Self::C14NMethod    => bindings::xmlSecTransformUsageC14NMethod,
+        }
+    }
+}
+
+
+/// Transform implemented in Rust. Once registered through [`XmlSecCustomTransformId::register`][register], xmlsec
+/// resolves its `href` like any of its own transforms, be it while signing templates or verifying signatures.
+///
+/// Binary transforms get their whole input at once. XML transforms get the node set flowing through the chain, and
+/// xmlsec canonicalizes it on its own whenever a binary transform follows.
+///
+/// [register]: struct.XmlSecCustomTransformId.html#method.register
+pub trait XmlSecCustomTransform: Send + Sync
+{
+    /// Short name, as reported in xmlsec error messages
+    fn name(&self) -> &str;
+
+    /// `Algorithm` URI identifying the transform within signatures
+    fn href(&self) -> &str;
+
+    /// Where the transform may be used, only as a reference transform by default
+    fn usage(&self) -> &[XmlSecTransformUsage]
+    {
+        &[XmlSecTransformUsage::DSigTransform]
+    }
+
+    /// Whether the transform operates on XML node sets through [`execute_xml`][xml] rather than on bytes through
+    /// [`execute_binary`][binary].
+    ///
+    /// [xml]: #method.execute_xml
+    /// [binary]: #method.execute_binary
+    fn is_xml(&self) -> bool
+    {
+        false
+    }
+
+    /// Turns the complete binary input into the output of the transform.
+    fn execute_binary(&self, _input: &[u8]) -> XmlSecResult<Vec<u8>>
+    {
+        Err(XmlSecError::TransformError(format!("{} does not handle binary data", self.name())))
+    }
+
+    /// Turns the input node set into the output one, passing it through by default.
+    ///
+    /// # Safety
+    ///
+    /// `nodes` remains owned by xmlsec. The returned set must either be `nodes` itself or a newly created one, which
+    /// xmlsec then takes ownership of.
+    unsafe fn execute_xml(&self, nodes: *mut bindings::xmlSecNodeSet) -> XmlSecResult<*mut bindings::xmlSecNodeSet>
+    {
+        Ok(nodes)
+    }
+}
+
+
+/// Handle to a registered [`XmlSecCustomTransform`][custom], to be appended to transform contexts and signature
+/// templates.
+///
+/// [custom]: trait.XmlSecCustomTransform.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct XmlSecCustomTransformId
+{
+    klass: bindings::xmlSecTransformId,
+}
+
+// registered klasses are leaked and never altered again
+unsafe impl Send for XmlSecCustomTransformId {}
+unsafe impl Sync for XmlSecCustomTransformId {}
+
+
+/// Klass handed over to xmlsec, which only ever sees the leading `klass`. Transforms get back at the Rust
+/// implementation through their `id` pointer.
+#[repr(C)]
+struct CustomKlass
+{
+    klass:     bindings::xmlSecTransformKlass,
+    transform: Box<dyn XmlSecCustomTransform>,
+
+    // backing storage of the klass strings
+    #[allow(dead_code)]
+    name: CString,
+    #[allow(dead_code)]
+    href: CString,
+}
+
+
+impl XmlSecCustomTransformId
+{
+    /// Registers `transform` within the global list of xmlsec transforms. Registrations last for the lifetime of the
+    /// process. Once registered, the transform may be enabled like any other on contexts restricting the transforms
+    /// they accept, see [`XmlSecTransformMethod`][method].
+    ///
+    /// [method]: trait.XmlSecTransformMethod.html
+    ///
+    /// # Errors
+    ///
+    /// If the name or href hold NUL bytes, no usage is given, or a transform with the same href is already known to
+    /// xmlsec.
+    pub fn register<T>(transform: T) -> XmlSecResult<Self>
+    where
+        T: XmlSecCustomTransform + 'static,
+    {
+        crate::xmlsec::guarantee_xmlsec_init()?;
+
+        let name = cstring(transform.name(), "transform name")?;
+        let href = cstring(transform.href(), "transform href")?;
+
+        let usage = transform.usage()
+            .iter()
+            .fold(0, |usage, u| usage | u.to_raw());
+
+        if usage == 0 {
+            return Err(XmlSecError::InvalidInput(format!("transform {} declares no usage", transform.href())));
+        }
+
+        // the lookup and the registration must not interleave with another registration
+        let _guard = REGISTRATION.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let known = unsafe { bindings::xmlSecTransformIdListFindByHref(
+            bindings::xmlSecTransformIdsGet(),
+            href.as_ptr() as *const c_uchar,
+            bindings::xmlSecTransformUsageAny,
+        ) };
+
+        if !known.is_null() {
+            return Err(XmlSecError::InvalidInput(format!("transform {} is already registered", transform.href())));
+        }
+
+        let xml = transform.is_xml();
+
+        let klass = bindings::xmlSecTransformKlass {
+            klassSize:   size_of::<bindings::xmlSecTransformKlass>() as bindings::xmlSecSize,
+            objSize:     size_of::<bindings::xmlSecTransform>() as bindings::xmlSecSize,
+            name:        name.as_ptr() as *const c_uchar,
+            href:        href.as_ptr() as *const c_uchar,
+            usage,
+            initialize:  None,
+            finalize:    None,
+            readNode:    None,
+            writeNode:   None,
+            setKeyReq:   None,
+            setKey:      None,
+            verify:      None,
+            getDataType: Some(bindings::xmlSecTransformDefaultGetDataType),
+            pushBin:     if xml { None } else { Some(bindings::xmlSecTransformDefaultPushBin) },
+            popBin:      if xml { None } else { Some(bindings::xmlSecTransformDefaultPopBin) },
+            pushXml:     if xml { Some(bindings::xmlSecTransformDefaultPushXml) } else { None },
+            popXml:      if xml { Some(bindings::xmlSecTransformDefaultPopXml) } else { None },
+            execute:     Some(execute_custom),
+            reserved0:   null_mut(),
+            reserved1:   null_mut(),
+        };
+
+        let custom = Box::into_raw(Box::new(CustomKlass {klass, name, href, transform: Box::new(transform)}));
+
+        let klassptr = unsafe { &(*custom).klass } as bindings::xmlSecTransformId;
+
+        if unsafe { bindings::xmlSecTransformIdsRegister(klassptr) } < 0
+        {
+            let custom = unsafe { Box::from_raw(custom) };
+
+            return Err(XmlSecError::AllocationFailed(format!("registration of transform {}", custom.transform.href())));
+        }
+
+        Ok(Self {klass: klassptr})
+    }
+
+    /// `Algorithm` URI of the transform
+    pub fn href(&self) -> &str
+    {
+        unsafe { transform_href(self.klass) }.unwrap_or_default()
+    }
+
+    /// Returns the resource pointer of the registered transform klass
+    pub fn to_method(&self) -> bindings::xmlSecTransformId
+    {
+        self.klass
+    }
+}
+
+
+/// Execution of every custom transform. Binary input accumulates in `inBuf` until the last chunk is pushed, XML input
+/// comes at once along with `last` being set.
+unsafe extern "C" fn execute_custom(
+    transform: *mut bindings::xmlSecTransform,
+    last:      c_int,
+    _ctx:      *mut bindings::xmlSecTransformCtx,
+) -> c_int
+{
+    if (*transform).status == bindings::xmlSecTransformStatus_xmlSecTransformStatusNone {
+        (*transform).status = bindings::xmlSecTransformStatus_xmlSecTransformStatusWorking;
+    }
+
+    match (*transform).status
+    {
+        bindings::xmlSecTransformStatus_xmlSecTransformStatusWorking if last == 0 => 0,
+
+        bindings::xmlSecTransformStatus_xmlSecTransformStatusWorking => {
+            let custom = &*((*transform).id as *const CustomKlass);
+
+            match catch_unwind(AssertUnwindSafe(|| finish_custom(custom, transform)))
+            {
+                Ok(Ok(())) => {
+                    (*transform).status = bindings::xmlSecTransformStatus_xmlSecTransformStatusFinished;
+                    0
+                }
+
+                _ => {
+                    (*transform).status = bindings::xmlSecTransformStatus_xmlSecTransformStatusFail;
+                    -1
+                }
+            }
+        }
+
+        // nothing may come in once the output has been produced
+        bindings::xmlSecTransformStatus_xmlSecTransformStatusFinished
+            if bindings::xmlSecBufferGetSize(&mut (*transform).inBuf) == 0 => 0,
+
+        _ => -1,
+    }
+}
+
+
+/// # Safety
+///
+/// `transform` must be a live instance of the klass in `custom`.
+unsafe fn finish_custom(custom: &CustomKlass, transform: *mut bindings::xmlSecTransform) -> XmlSecResult<()>
+{
+    if custom.transform.is_xml()
+    {
+        (*transform).outNodes = custom.transform.execute_xml((*transform).inNodes)?;
+
+        return Ok(());
+    }
+
+    let inbuf = &mut (*transform).inBuf as *mut bindings::xmlSecBuffer;
+    let size  = bindings::xmlSecBufferGetSize(inbuf);
+    let data  = bindings::xmlSecBufferGetData(inbuf);
+
+    let input = if data.is_null() { &[][..] } else { slice::from_raw_parts(data, size as usize) };
+
+    let output = custom.transform.execute_binary(input)?;
+
+    if bindings::xmlSecBufferAppend(&mut (*transform).outBuf, output.as_ptr(), output.len() as bindings::xmlSecSize) < 0 {
+        return Err(XmlSecError::AllocationFailed(format!("output of transform {}", custom.transform.name())));
+    }
+
+    if bindings::xmlSecBufferRemoveHead(inbuf, size) < 0 {
+        return Err(XmlSecError::TransformError(format!("{} failed to consume its input", custom.transform.name())));
+    }
+
+    Ok(())
+}
+
+
+/// xmlsec only takes the `PrefixList` of exclusive canonicalization from a `<dsig:Transform/>` node, hence one gets
+/// built within a scratch document just for the sake of reading it into `ctx`. The transform keeps pointing at that
+/// node as its `hereNode`, so the document is handed over to `scratch`, to be freed along with `ctx`.
+///
+/// # Safety
+///
+/// `ctx` must be a valid transform context.
+unsafe fn read_c14n_transform(
+    ctx:      *mut bindings::xmlSecTransformCtx,
+    scratch:  &mut Vec<*mut bindings::xmlDoc>,
+    method:   &XmlSecCanonicalizationMethod,
+    prefixes: &CStr,
+) -> *mut bindings::xmlSecTransform
+{
+    let doc = bindings::xmlNewDoc(b"1.0\0".as_ptr());
+
+    if doc.is_null() {
+        return null_mut();
+    }
+
+    scratch.push(doc);
+
+    let node = bindings::xmlNewDocNode(doc, null_mut(), &bindings::xmlSecNodeTransform as *const c_uchar, null());
+    let mut transform = null_mut();
+
+    if !node.is_null()
+    {
+        bindings::xmlDocSetRootElement(doc, node);
+        bindings::xmlSetNs(node, bindings::xmlNewNs(node, &bindings::xmlSecDSigNs as *const c_uchar, null()));
+        bindings::xmlSetProp(node, &bindings::xmlSecAttrAlgorithm as *const c_uchar, (*method.to_method()).href);
+
+        if bindings::xmlSecTmplTransformAddC14NInclNamespaces(node, prefixes.as_ptr() as *const c_uchar) >= 0 {
+            transform = bindings::xmlSecTransformCtxNodeRead(ctx, node, bindings::xmlSecTransformUsageC14NMethod);
+        }
+    }
+
+    transform
+}
+
+
+/// # Safety
+///
+/// `transform` must be null or point to a registered xmlsec transform klass.
+pub(crate) unsafe fn transform_href<'k>(transform: bindings::xmlSecTransformId) -> Option<&'k str>
+{
+    if transform.is_null() || (*transform).href.is_null() {
+        return None;
+    }
+
+    CStr::from_ptr((*transform).href as *const c_char).to_str().ok()
 }
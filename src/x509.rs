@@ -0,0 +1,223 @@
+//!
+//! Inspection of X509 Certificates attached to Keys
+//!
+use crate::bindings;
+
+use crate::XmlSecError;
+use crate::XmlSecResult;
+
+use std::ffi::CString;
+
+use std::os::raw::c_char;
+use std::os::raw::c_int;
+use std::os::raw::c_uint;
+use std::os::raw::c_ulong;
+use std::os::raw::c_void;
+
+use std::ptr::null_mut;
+use std::time::Duration;
+use std::time::SystemTime;
+
+
+// mirror of `XN_FLAG_RFC2253` of openssl/x509.h, a combination of flags bindgen does not evaluate
+const XN_FLAG_RFC2253: c_ulong = 0x0111_0317;
+
+pub(crate) const SHA256_LENGTH: usize = 32;
+
+
+/// Details of a certificate attached to an [`XmlSecKey`][key].
+///
+/// [key]: struct.XmlSecKey.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlSecCertificateInfo
+{
+    /// Subject distinguished name, as of RFC 2253
+    pub subject: String,
+
+    /// Issuer distinguished name, as of RFC 2253
+    pub issuer: String,
+
+    /// Serial number in uppercase hexadecimal
+    pub serial: String,
+
+    /// Start of the validity period
+    pub not_before: SystemTime,
+
+    /// End of the validity period
+    pub not_after: SystemTime,
+
+    /// SHA-256 digest of the DER encoded certificate
+    pub fingerprint: [u8; SHA256_LENGTH],
+
+    /// Whether this is the certificate of the key itself, as opposed to one of its issuers
+    pub key_certificate: bool,
+}
+
+
+impl XmlSecCertificateInfo
+{
+    /// Whether `time` lies within the validity period of the certificate.
+    pub fn is_valid_at(&self, time: SystemTime) -> bool
+    {
+        self.not_before <= time && time <= self.not_after
+    }
+}
+
+
+/// Reads out the details of `cert`, which stays owned by the caller. Whether it is the certificate of a key is up to
+/// the caller to tell.
+pub fn describe(cert: *mut bindings::X509) -> XmlSecResult<XmlSecCertificateInfo>
+{
+    let subject = name_to_string(unsafe { bindings::X509_get_subject_name(cert) })?;
+    let issuer  = name_to_string(unsafe { bindings::X509_get_issuer_name(cert) })?;
+
+    let serial = unsafe {
+        let serial = bindings::X509_get0_serialNumber(cert);
+        let data   = bindings::ASN1_STRING_get0_data(serial);
+        let length = bindings::ASN1_STRING_length(serial);
+
+        if data.is_null() || length <= 0 {
+            return Err(certificate_error("serial number"));
+        }
+
+        std::slice::from_raw_parts(data, length as usize)
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect()
+    };
+
+    let not_before = asn1_time(unsafe { bindings::X509_get0_notBefore(cert) })?;
+    let not_after  = asn1_time(unsafe { bindings::X509_get0_notAfter(cert) })?;
+
+    Ok(XmlSecCertificateInfo {
+        subject,
+        issuer,
+        serial,
+        not_before,
+        not_after,
+        fingerprint: fingerprint(cert)?,
+        key_certificate: false,
+    })
+}
+
+
+/// Digest of `cert` in its DER encoding, by the XML Signature digest method `href`. The fragments of these URIs
+/// happen to be the OpenSSL names of the digests, e.g. `sha256` or `sha3-256`.
+pub fn digest(cert: *mut bindings::X509, href: &str) -> XmlSecResult<Vec<u8>>
+{
+    let unknown = || XmlSecError::MalformedSignature(format!("unknown digest method {}", href));
+
+    let name = href.rsplit_once('#')
+        .map(|(_, name)| name)
+        .ok_or_else(unknown)?;
+
+    let cname = CString::new(name).map_err(|_| unknown())?;
+
+    let md = unsafe { bindings::EVP_get_digestbyname(cname.as_ptr()) };
+
+    if md.is_null() {
+        return Err(unknown());
+    }
+
+    let mut digest = vec![0u8; bindings::EVP_MAX_MD_SIZE as usize];
+    let mut length = 0 as c_uint;
+
+    if unsafe { bindings::X509_digest(cert, md, digest.as_mut_ptr(), &mut length) } != 1 {
+        return Err(certificate_error("digest"));
+    }
+
+    digest.truncate(length as usize);
+
+    Ok(digest)
+}
+
+
+/// SHA-256 digest of `cert` in its DER encoding.
+pub fn fingerprint(cert: *mut bindings::X509) -> XmlSecResult<[u8; SHA256_LENGTH]>
+{
+    let mut digest = [0u8; SHA256_LENGTH];
+    let mut length = 0 as c_uint;
+
+    let rc = unsafe { bindings::X509_digest(cert, bindings::EVP_sha256(), digest.as_mut_ptr(), &mut length) };
+
+    if rc != 1 || length as usize != SHA256_LENGTH {
+        return Err(certificate_error("fingerprint"));
+    }
+
+    Ok(digest)
+}
+
+
+fn name_to_string(name: *mut bindings::X509_NAME) -> XmlSecResult<String>
+{
+    if name.is_null() {
+        return Err(certificate_error("distinguished name"));
+    }
+
+    let bio = unsafe { bindings::BIO_new(bindings::BIO_s_mem()) };
+
+    if bio.is_null() {
+        return Err(XmlSecError::AllocationFailed("memory BIO".into()));
+    }
+
+    let rc = unsafe { bindings::X509_NAME_print_ex(bio, name, 0, XN_FLAG_RFC2253) };
+
+    let mut data: *mut c_char = null_mut();
+
+    // BIO_get_mem_data, a macro around BIO_ctrl
+    let length = unsafe {
+        bindings::BIO_ctrl(bio, bindings::BIO_CTRL_INFO as c_int, 0, &mut data as *mut *mut c_char as *mut c_void)
+    };
+
+    let text = if rc < 0 || data.is_null() || length < 0 {
+        None
+    } else {
+        let bytes = unsafe { std::slice::from_raw_parts(data as *const u8, length as usize) };
+
+        Some(String::from_utf8_lossy(bytes).into_owned())
+    };
+
+    unsafe { bindings::BIO_free(bio) };
+
+    text.ok_or_else(|| certificate_error("distinguished name"))
+}
+
+
+fn asn1_time(time: *const bindings::ASN1_TIME) -> XmlSecResult<SystemTime>
+{
+    let mut tm: bindings::tm = unsafe { std::mem::zeroed() };
+
+    if time.is_null() || unsafe { bindings::ASN1_TIME_to_tm(time, &mut tm) } != 1 {
+        return Err(certificate_error("validity period"));
+    }
+
+    let days = days_from_civil(tm.tm_year as i64 + 1900, tm.tm_mon as i64 + 1, tm.tm_mday as i64);
+    let secs = days * 86400 + tm.tm_hour as i64 * 3600 + tm.tm_min as i64 * 60 + tm.tm_sec as i64;
+
+    let offset = Duration::from_secs(secs.unsigned_abs());
+
+    if secs >= 0 {
+        Ok(SystemTime::UNIX_EPOCH + offset)
+    } else {
+        Ok(SystemTime::UNIX_EPOCH - offset)
+    }
+}
+
+
+/// Days since 1970-01-01 of the given proleptic Gregorian date, after Howard Hinnant's `days_from_civil`.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64
+{
+    let year = if month <= 2 { year - 1 } else { year };
+    let era  = year.div_euclid(400);
+    let yoe  = year - era * 400;
+    let doy  = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe  = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146097 + doe - 719468
+}
+
+
+fn certificate_error(what: &str) -> XmlSecError
+{
+    XmlSecError::CertificateError(format!("failed to read the {} of a certificate", what))
+}
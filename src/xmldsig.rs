@@ -4,14 +4,24 @@
 use crate::bindings;
 
 use crate::XmlSecKey;
+use crate::XmlSecKeysManager;
 use crate::XmlSecError;
 use crate::XmlSecResult;
+use crate::XmlSecTransformMethod;
 
 use crate::XmlNode;
 use crate::XmlDocument;
 
+use crate::templates::DSIG11_NS;
+use crate::templates::NODE_X509_DIGEST;
+
+use crate::x509;
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
 use std::os::raw::c_uchar;
 use std::mem::forget;
+use std::mem::ManuallyDrop;
 use std::ptr::null_mut;
 
 
@@ -19,23 +29,74 @@ use std::ptr::null_mut;
 pub struct XmlSecSignatureContext
 {
     ctx: *mut bindings::xmlSecDSigCtx,
+
+    // kept alive for as long as the context refers to it
+    #[allow(dead_code)]
+    manager: Option<XmlSecKeysManager>,
+}
+
+
+/// Processing status of a signature or of one of its references.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XmlSecDSigStatus
+{
+    /// Not processed (yet)
+    Unknown,
+    /// Processed and valid
+    Succeeded,
+    /// Processed and invalid
+    Invalid,
+}
+
+
+/// Outcome of a single `<dsig:Reference/>` as processed by the last signing or verification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlSecReferenceResult
+{
+    /// `URI` attribute of the reference
+    pub uri: Option<String>,
+
+    /// `Id` attribute of the reference
+    pub id: Option<String>,
+
+    /// `Type` attribute of the reference
+    pub reference_type: Option<String>,
+
+    /// Whether the reference digest matched
+    pub status: XmlSecDSigStatus,
 }
 
 
 impl XmlSecSignatureContext
 {
     /// Builds a context, ensuring xmlsec is initialized.
-    pub fn new() -> Self
+    pub fn new() -> XmlSecResult<Self>
     {
-        crate::xmlsec::guarantee_xmlsec_init();
+        crate::xmlsec::guarantee_xmlsec_init()?;
 
         let ctx = unsafe { bindings::xmlSecDSigCtxCreate(null_mut()) };
 
         if ctx.is_null() {
-            panic!("Failed to create dsig context");
+            return Err(XmlSecError::AllocationFailed("dsig context".into()));
         }
 
-        Self {ctx}
+        Ok(Self {ctx, manager: None})
+    }
+
+    /// Builds a context looking up keys in `manager` whenever no key has been set with [`insert_key`][inskey].
+    ///
+    /// [inskey]: struct.XmlSecSignatureContext.html#method.insert_key
+    pub fn with_keys_manager(manager: XmlSecKeysManager) -> XmlSecResult<Self>
+    {
+        crate::xmlsec::guarantee_xmlsec_init()?;
+
+        let ctx = unsafe { bindings::xmlSecDSigCtxCreate(manager.as_ptr()) };
+
+        if ctx.is_null() {
+            return Err(XmlSecError::AllocationFailed("dsig context".into()));
+        }
+
+        Ok(Self {ctx, manager: Some(manager)})
     }
 
     /// Sets the key to use for signature or verification. In case a key had
@@ -71,6 +132,61 @@ impl XmlSecSignatureContext
         }
     }
 
+    /// Whether `<dsig:Manifest/>` references get processed, which xmlsec does by default. Switching it off sets
+    /// `XMLSEC_DSIG_FLAGS_IGNORE_MANIFESTS`, xmlsec having no flag to explicitly turn processing on.
+    ///
+    /// A failed manifest reference never invalidates the signature itself, it is up to the application to decide
+    /// upon [`manifest_references`][manrefs].
+    ///
+    /// [manrefs]: struct.XmlSecSignatureContext.html#method.manifest_references
+    pub fn process_manifests(&mut self, process: bool)
+    {
+        unsafe {
+            if process {
+                (*self.ctx).flags &= !bindings::XMLSEC_DSIG_FLAGS_IGNORE_MANIFESTS;
+            } else {
+                (*self.ctx).flags |= bindings::XMLSEC_DSIG_FLAGS_IGNORE_MANIFESTS;
+            }
+        }
+    }
+
+    /// Restricts the transforms `<dsig:Reference/>` nodes may use, digest methods included, to those enabled. As long
+    /// as none is enabled, every registered transform is accepted.
+    pub fn enable_reference_transform<T: XmlSecTransformMethod>(&mut self, transform: T) -> XmlSecResult<()>
+    {
+        if unsafe { bindings::xmlSecDSigCtxEnableReferenceTransform(self.ctx, transform.transform_id()) } < 0 {
+            return Err(XmlSecError::AllocationFailed("enabled reference transforms entry".into()));
+        }
+
+        Ok(())
+    }
+
+    /// Restricts the canonicalization and signature methods `<dsig:SignedInfo/>` may use to those enabled. As long as
+    /// none is enabled, every registered transform is accepted.
+    pub fn enable_signature_transform<T: XmlSecTransformMethod>(&mut self, transform: T) -> XmlSecResult<()>
+    {
+        if unsafe { bindings::xmlSecDSigCtxEnableSignatureTransform(self.ctx, transform.transform_id()) } < 0 {
+            return Err(XmlSecError::AllocationFailed("enabled signature transforms entry".into()));
+        }
+
+        Ok(())
+    }
+
+    /// Results of the `<dsig:SignedInfo/>` references processed by the last signing or verification.
+    pub fn signed_info_references(&self) -> Vec<XmlSecReferenceResult>
+    {
+        unsafe { reference_results(&mut (*self.ctx).signedInfoReferences) }
+    }
+
+    /// Results of the `<dsig:Manifest/>` references processed by the last signing or verification. Always empty
+    /// when manifests are not [processed][procman].
+    ///
+    /// [procman]: struct.XmlSecSignatureContext.html#method.process_manifests
+    pub fn manifest_references(&self) -> Vec<XmlSecReferenceResult>
+    {
+        unsafe { reference_results(&mut (*self.ctx).manifestReferences) }
+    }
+
     /// UNTESTED
     pub fn sign_node(&self, node: &XmlNode) -> XmlSecResult<()>
     {
@@ -142,7 +258,8 @@ impl XmlSecSignatureContext
     /// # Safety
     ///
     /// Returns a raw pointer to the underlying xmlsec signature context. Beware that it will be forgotten by this
-    /// wrapping object and *must* be deallocated manually by the callee.
+    /// wrapping object and *must* be deallocated manually by the callee. A keys manager the context was built with
+    /// gets leaked along, as the context keeps referring to it.
     pub unsafe fn into_ptr(self) -> *mut bindings::xmlSecDSigCtx
     {
         let ctx = self.ctx;  // keep a copy of the pointer
@@ -159,7 +276,7 @@ impl XmlSecSignatureContext
     fn key_is_set(&self) -> XmlSecResult<()>
     {
         unsafe {
-            if ! (*self.ctx).signKey.is_null() {
+            if ! (*self.ctx).signKey.is_null() || ! (*self.ctx).keyInfoReadCtx.keysMngr.is_null() {
                 Ok(())
             } else {
                 Err(XmlSecError::KeyNotLoaded)
@@ -172,10 +289,10 @@ impl XmlSecSignatureContext
         let rc = unsafe { bindings::xmlSecDSigCtxSign(self.ctx, node) };
 
         if rc < 0 {
-            Err(XmlSecError::SigningError)
-        } else {
-            Ok(())
+            return Err(XmlSecError::SigningError);
         }
+
+        unsafe { fill_x509_digests(node, (*self.ctx).signKey) }
     }
 
     fn verify_node_raw(&self, node: *mut bindings::xmlNode) -> XmlSecResult<bool>
@@ -192,7 +309,7 @@ impl XmlSecSignatureContext
             bindings::xmlSecDSigStatus_xmlSecDSigStatusSucceeded => Ok(true),
             bindings::xmlSecDSigStatus_xmlSecDSigStatusInvalid   => Ok(false),
 
-            _ => panic!("Failed to interprete xmlSecDSigStatus code")
+            status => Err(XmlSecError::UnknownStatus(status)),
         }
     }
 }
@@ -207,6 +324,148 @@ impl Drop for XmlSecSignatureContext
 }
 
 
+impl XmlSecDSigStatus
+{
+    fn from_raw(status: bindings::xmlSecDSigStatus) -> Self
+    {
+        match status
+        {
+            bindings::xmlSecDSigStatus_xmlSecDSigStatusSucceeded => Self::Succeeded,
+            bindings::xmlSecDSigStatus_xmlSecDSigStatusInvalid   => Self::Invalid,
+
+            _ => Self::Unknown,
+        }
+    }
+}
+
+
+/// # Safety
+///
+/// `list` must be one of the reference lists of a live xmlsec signature context.
+unsafe fn reference_results(list: *mut bindings::xmlSecPtrList) -> Vec<XmlSecReferenceResult>
+{
+    let size = bindings::xmlSecPtrListGetSize(list);
+
+    (0..size)
+        .map(|i| bindings::xmlSecPtrListGetItem(list, i) as *mut bindings::xmlSecDSigReferenceCtx)
+        .filter(|r| !r.is_null())
+        .map(|r| XmlSecReferenceResult {
+            uri:            optional_string((*r).uri),
+            id:             optional_string((*r).id),
+            reference_type: optional_string((*r).type_),
+            status:         XmlSecDSigStatus::from_raw((*r).status),
+        })
+        .collect()
+}
+
+
+/// xmlsec1 1.2 knows no `<dsig11:X509Digest/>` and leaves it empty, hence each one left so within the
+/// `<dsig:KeyInfo/>` of a signature gets the digest of the certificate of the signing key here.
+///
+/// # Safety
+///
+/// `signode` must be a signature node just signed with `key`, which stays owned by the signature context.
+unsafe fn fill_x509_digests(signode: *mut bindings::xmlNode, key: *mut bindings::xmlSecKey) -> XmlSecResult<()>
+{
+    let keyinfo = bindings::xmlSecFindChild(
+        signode,
+        &bindings::xmlSecNodeKeyInfo as *const c_uchar,
+        &bindings::xmlSecDSigNs      as *const c_uchar,
+    );
+
+    if keyinfo.is_null() || key.is_null() {
+        return Ok(());
+    }
+
+    let dsig     = &bindings::xmlSecDSigNs       as *const c_uchar;
+    let x509data = &bindings::xmlSecNodeX509Data as *const c_uchar;
+
+    let digests = element_children(keyinfo)
+        .into_iter()
+        .filter(|&n| bindings::xmlSecCheckNodeName(n, x509data, dsig) == 1)
+        .flat_map(|n| element_children(n))
+        .filter(|&n| bindings::xmlSecCheckNodeName(n, NODE_X509_DIGEST.as_ptr(), DSIG11_NS.as_ptr()) == 1)
+        .filter(|&n| (*n).children.is_null())
+        .collect::<Vec<_>>();
+
+    if digests.is_empty() {
+        return Ok(());
+    }
+
+    let key = ManuallyDrop::new(XmlSecKey::from_ptr(key));
+
+    let cert = match (key.key_cert(), key.x509_certs().as_slice())
+    {
+        (cert, _) if !cert.is_null() => cert,
+        (_, [cert])                  => *cert,
+
+        _ => return Err(XmlSecError::CertificateError("no certificate of the signing key for X509Digest".into())),
+    };
+
+    for node in digests
+    {
+        let attr = bindings::xmlHasProp(node, &bindings::xmlSecAttrAlgorithm as *const c_uchar);
+
+        let href = if attr.is_null() || (*attr).children.is_null() {
+            None
+        } else {
+            optional_string((*(*attr).children).content)
+        };
+
+        let href = href.ok_or_else(|| XmlSecError::MalformedSignature("X509Digest without Algorithm".into()))?;
+
+        let digest = x509::digest(cert, &href)?;
+        let buffer = bindings::xmlSecBufferCreate(digest.len() as bindings::xmlSecSize);
+
+        if buffer.is_null() {
+            return Err(XmlSecError::AllocationFailed("X509Digest buffer".into()));
+        }
+
+        let mut rc = bindings::xmlSecBufferSetData(buffer, digest.as_ptr(), digest.len() as bindings::xmlSecSize);
+
+        if rc >= 0 {
+            rc = bindings::xmlSecBufferBase64NodeContentWrite(buffer, node, 0);
+        }
+
+        bindings::xmlSecBufferDestroy(buffer);
+
+        if rc < 0 {
+            return Err(XmlSecError::AllocationFailed("X509Digest content".into()));
+        }
+    }
+
+    Ok(())
+}
+
+
+/// # Safety
+///
+/// `node` must point to a valid node.
+unsafe fn element_children(node: *mut bindings::xmlNode) -> Vec<*mut bindings::xmlNode>
+{
+    let mut children = Vec::new();
+    let mut child    = bindings::xmlSecGetNextElementNode((*node).children);
+
+    while !child.is_null()
+    {
+        children.push(child);
+        child = bindings::xmlSecGetNextElementNode((*child).next);
+    }
+
+    children
+}
+
+
+unsafe fn optional_string(value: *const c_uchar) -> Option<String>
+{
+    if value.is_null() {
+        return None;
+    }
+
+    Some(CStr::from_ptr(value as *const c_char).to_string_lossy().into_owned())
+}
+
+
 fn find_root(doc: &XmlDocument) -> XmlSecResult<*mut bindings::xmlNode>
 {
     if let Some(root) = doc.get_root_element()
@@ -5,6 +5,9 @@ use crate::bindings;
 
 use crate::lazy_static;
 
+use crate::XmlSecError;
+use crate::XmlSecResult;
+
 use std::ptr::null;
 use std::sync::Mutex;
 
@@ -14,14 +17,16 @@ lazy_static! {
 }
 
 
-pub fn guarantee_xmlsec_init()
+pub fn guarantee_xmlsec_init() -> XmlSecResult<()>
 {
     let mut inner = XMLSEC.lock()
-        .expect("Unable to lock global xmlsec initalization wrapper");
+        .map_err(|_| XmlSecError::InitError("Unable to lock global xmlsec initalization wrapper".into()))?;
 
     if inner.is_none() {
-        *inner = Some(XmlSecContext::new());
+        *inner = Some(XmlSecContext::new()?);
     }
+
+    Ok(())
 }
 
 
@@ -38,13 +43,27 @@ struct XmlSecContext {}
 impl XmlSecContext
 {
     /// Runs xmlsec initialization and returns instance of itself.
-    pub fn new() -> Self
+    pub fn new() -> XmlSecResult<Self>
     {
-        init_xmlsec();
-        init_crypto_app();
-        init_crypto();
+        init_xmlsec()?;
+
+        // unwind whatever got initialized so far
+        if let Err(e) = init_crypto_app()
+        {
+            cleanup_xmlsec();
+            return Err(e);
+        }
+
+        if let Err(e) = init_crypto()
+        {
+            cleanup_crypto_app();
+            cleanup_xmlsec();
+            return Err(e);
+        }
 
-        Self {}
+        crate::stack::install();
+
+        Ok(Self {})
     }
 }
 
@@ -61,20 +80,22 @@ impl Drop for XmlSecContext
 
 
 /// Init xmlsec library
-fn init_xmlsec()
+fn init_xmlsec() -> XmlSecResult<()>
 {
     let rc = unsafe { bindings::xmlSecInit() };
 
     if rc < 0 {
-        panic!("XmlSec failed initialization");
+        return Err(XmlSecError::InitError("XmlSec failed initialization".into()));
     }
+
+    Ok(())
 }
 
 
 /// Load default crypto engine if we are supporting dynamic loading for
 /// xmlsec-crypto libraries. Use the crypto library name ("openssl",
 /// "nss", etc.) to load corresponding xmlsec-crypto library.
-fn init_crypto_app()
+fn init_crypto_app() -> XmlSecResult<()>
 {
     // if bindings::XMLSEC_CRYPTO_DYNAMIC_LOADING
     // {
@@ -89,20 +110,26 @@ fn init_crypto_app()
     let rc = unsafe { bindings::xmlSecOpenSSLAppInit(null()) };
 
     if rc < 0 {
-        panic!("XmlSec failed to init crypto backend")
+        return Err(XmlSecError::InitError("XmlSec failed to init crypto backend".into()));
     }
+
+    Ok(())
 }
 
 
 /// Init xmlsec-crypto library
-fn init_crypto()
+fn init_crypto() -> XmlSecResult<()>
 {
     let rc = unsafe { bindings::xmlSecOpenSSLInit() };
 
     if rc < 0 {
-        panic!("XmlSec failed while loading default crypto backend. \
-               Make sure that you have it installed and check shread libraries path");
+        return Err(XmlSecError::InitError(
+            "XmlSec failed while loading default crypto backend. \
+             Make sure that you have it installed and check shread libraries path".into()
+        ));
     }
+
+    Ok(())
 }
 
 
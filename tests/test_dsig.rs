@@ -3,8 +3,17 @@
 //!
 use xmlsec::XmlSecKey;
 use xmlsec::XmlSecKeyFormat;
+use xmlsec::XmlSecKeysManager;
 use xmlsec::XmlSecSignatureContext;
 use xmlsec::XmlSecDocumentExt;
+use xmlsec::XmlSecDSigStatus;
+use xmlsec::XmlSecError;
+
+use xmlsec::XmlSecTemplateBuilder;
+use xmlsec::XmlSecDocumentTemplating;
+use xmlsec::XmlSecTemplateReference;
+use xmlsec::XmlSecTemplateObject;
+use xmlsec::XmlSecTemplateObjectContent;
 
 use libxml::parser::Parser as XmlParser;
 
@@ -12,14 +21,16 @@ use libxml::parser::Parser as XmlParser;
 #[test]
 fn test_dsig_context_creation()
 {
-    XmlSecSignatureContext::new();
+    XmlSecSignatureContext::new()
+        .expect("Failed to create signature context");
 }
 
 
 #[test]
 fn test_dsig_key_setting()
 {
-    let mut ctx = XmlSecSignatureContext::new();
+    let mut ctx = XmlSecSignatureContext::new()
+        .expect("Failed to create signature context");
 
     let key = XmlSecKey::from_file("tests/resources/key.pem", XmlSecKeyFormat::Pem, None)
         .expect("Failed to properly load key for test");
@@ -112,9 +123,140 @@ fn test_verify_custom_id_signature()
 }
 
 
+#[test]
+fn test_verify_manifest_references()
+{
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load document to be signed");
+
+    doc.template()
+        .uri("#manifest")
+        .reference_type("http://www.w3.org/2000/09/xmldsig#Manifest")
+        .enveloped(false)
+        .object(XmlSecTemplateObject::new(XmlSecTemplateObjectContent::Manifest(
+            Some("manifest".into()),
+            vec![XmlSecTemplateReference::new("").id("attachment").enveloped(true)],
+        )))
+        .done()
+        .expect("Failed to build signature template with manifest");
+
+    common_setup_context_and_key()
+        .sign_document(&doc)
+        .expect("Failed to sign document with manifest");
+
+    // alter what only the manifest covers
+    let mut data = doc.get_root_element()
+        .and_then(|root| root.get_first_element_child())
+        .expect("Document should have a data node");
+
+    data.set_content("tampered")
+        .expect("Failed to alter data node");
+
+    let ctx = common_setup_context_and_key();
+
+    let valid = ctx.verify_document(&doc)
+        .expect("Failed to verify document with manifest");
+
+    assert!(valid, "Failed manifest references must not invalidate the signature");
+
+    let signed = ctx.signed_info_references();
+
+    assert_eq!(signed.len(), 1);
+    assert_eq!(signed[0].uri.as_deref(), Some("#manifest"));
+    assert_eq!(signed[0].status, XmlSecDSigStatus::Succeeded);
+
+    let manifest = ctx.manifest_references();
+
+    assert_eq!(manifest.len(), 1);
+    assert_eq!(manifest[0].uri.as_deref(), Some(""));
+    assert_eq!(manifest[0].id.as_deref(), Some("attachment"));
+    assert_eq!(manifest[0].status, XmlSecDSigStatus::Invalid);
+
+    let mut ctx = common_setup_context_and_key();
+    ctx.process_manifests(false);
+
+    let valid = ctx.verify_document(&doc)
+        .expect("Failed to verify document ignoring manifests");
+
+    assert!(valid, "Signature should remain valid when ignoring manifests");
+    assert!(ctx.manifest_references().is_empty(), "Manifests should have been ignored");
+}
+
+
+#[test]
+fn test_verify_with_keys_manager()
+{
+    let mut manager = XmlSecKeysManager::new()
+        .expect("Failed to create keys manager");
+
+    manager.add_key(XmlSecKey::from_file("tests/resources/key.pem", XmlSecKeyFormat::Pem, None).unwrap())
+        .expect("Failed to add key to manager");
+
+    manager.add_certificate_from_file("tests/resources/key.crt", XmlSecKeyFormat::CertPem, true)
+        .expect("Failed to add certificate to manager");
+
+    let ctx = XmlSecSignatureContext::with_keys_manager(manager)
+        .expect("Failed to create signature context");
+
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign1-res.xml")
+        .expect("Failed to load signature for verification testing");
+
+    let valid = ctx.verify_document(&doc)
+        .expect("Failed to verify document with keys manager");
+
+    assert!(valid, "Key should have been picked from the keys manager");
+
+    let empty = XmlSecSignatureContext::with_keys_manager(XmlSecKeysManager::new().unwrap())
+        .expect("Failed to create signature context");
+
+    assert!(!matches!(empty.verify_document(&doc), Ok(true)), "No key to verify with should be found");
+}
+
+
+#[test]
+fn test_sign_with_keys_manager()
+{
+    let mut manager = XmlSecKeysManager::new()
+        .expect("Failed to create keys manager");
+
+    manager.add_key(XmlSecKey::from_file("tests/resources/key.pem", XmlSecKeyFormat::Pem, None).unwrap())
+        .expect("Failed to add key to manager");
+
+    let cert = std::fs::read("tests/resources/key.crt")
+        .expect("Failed to read certificate for test");
+
+    manager.add_certificate_from_memory(&cert, XmlSecKeyFormat::CertPem, false)
+        .expect("Failed to add certificate to manager");
+
+    assert!(matches!(
+        manager.add_certificate_from_memory(b"not a certificate", XmlSecKeyFormat::CertPem, false),
+        Err(XmlSecError::CertLoadError)
+    ));
+
+    let ctx = XmlSecSignatureContext::with_keys_manager(manager)
+        .expect("Failed to create signature context");
+
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign1-tmpl.xml")
+        .expect("Failed to load signature template");
+
+    ctx.sign_document(&doc)
+        .expect("Failed to sign document with keys manager");
+
+    let reference = String::from_utf8(
+        include_bytes!("./resources/sign1-res.xml").to_vec()
+    ).unwrap();
+
+    assert_eq!(doc.to_string(), reference, "Key should have been picked from the keys manager");
+}
+
+
 fn common_setup_context_and_key() -> XmlSecSignatureContext
 {
-   let mut ctx = XmlSecSignatureContext::new();
+   let mut ctx = XmlSecSignatureContext::new()
+       .expect("Failed to create signature context");
 
    let key = XmlSecKey::from_file("tests/resources/key.pem", XmlSecKeyFormat::Pem, None)
        .expect("Failed to properly load key for test");
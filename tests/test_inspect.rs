@@ -0,0 +1,173 @@
+//!
+//! Testing of Signature Inspection
+//!
+use xmlsec::XmlSecSignatureInfo;
+use xmlsec::XmlSecReferenceTransform;
+use xmlsec::XmlSecCanonicalizationMethod;
+use xmlsec::XmlSecSignatureMethod;
+use xmlsec::XmlSecError;
+
+use xmlsec::XmlSecKey;
+use xmlsec::XmlSecKeyFormat;
+use xmlsec::XmlSecSignatureContext;
+
+use xmlsec::XmlSecTemplateBuilder;
+use xmlsec::XmlSecDocumentTemplating;
+
+use libxml::parser::Parser as XmlParser;
+use libxml::tree::Document as XmlDocument;
+use libxml::tree::Node as XmlNode;
+
+
+#[test]
+fn test_parse_signed_document()
+{
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign1-res.xml")
+        .expect("Failed to load signed document");
+
+    let info = XmlSecSignatureInfo::parse(&find_signature(&doc))
+        .expect("Failed to parse signature");
+
+    assert_eq!(info.canonicalization, XmlSecCanonicalizationMethod::InclusiveC14N);
+    assert_eq!(info.signature_method, XmlSecSignatureMethod::RsaSha1);
+    assert_eq!(info.signature_value.len(), 128);
+
+    assert_eq!(info.references.len(), 1);
+
+    let reference = &info.references[0];
+
+    assert_eq!(reference.uri.as_deref(), Some(""));
+    assert_eq!(reference.transforms, vec![XmlSecReferenceTransform::Enveloped]);
+    assert_eq!(reference.digest_method, XmlSecSignatureMethod::Sha1);
+    assert_eq!(reference.digest_value.len(), 20);
+
+    let keyinfo = info.key_info.expect("Signature should have KeyInfo");
+
+    assert!(keyinfo.key_names.is_empty(), "Empty KeyName should not be reported");
+    assert!(keyinfo.certificates.is_empty());
+}
+
+
+#[test]
+fn test_parse_keyinfo_and_transforms()
+{
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load document to be signed");
+
+    doc.template()
+        .signature(XmlSecSignatureMethod::RsaSha256)
+        .reference_signature(XmlSecSignatureMethod::Sha256)
+        .reference_canonicalization(XmlSecCanonicalizationMethod::ExclusiveC14N)
+        .reference_inclusive_namespaces("xs")
+        .keyname_text("test-key")
+        .x509_certificate(true)
+        .done()
+        .expect("Failed to build signature template");
+
+    let key = XmlSecKey::from_file("tests/resources/key.pem", XmlSecKeyFormat::Pem, None)
+        .expect("Failed to properly load key for test");
+
+    key.load_cert_from_file("tests/resources/key.crt", XmlSecKeyFormat::Pem)
+        .expect("Failed to properly load key certificate for test");
+
+    let mut ctx = XmlSecSignatureContext::new()
+        .expect("Failed to create signature context");
+    ctx.insert_key(key);
+
+    ctx.sign_document(&doc)
+        .expect("Failed to sign document");
+
+    let info = XmlSecSignatureInfo::parse(&find_signature(&doc))
+        .expect("Failed to parse signature");
+
+    assert_eq!(info.canonicalization, XmlSecCanonicalizationMethod::ExclusiveC14N);
+    assert_eq!(info.signature_method, XmlSecSignatureMethod::RsaSha256);
+
+    let reference = &info.references[0];
+
+    assert_eq!(reference.uri, None);
+    assert_eq!(reference.digest_method, XmlSecSignatureMethod::Sha256);
+    assert_eq!(reference.digest_value.len(), 32);
+    assert_eq!(reference.transforms, vec![
+        XmlSecReferenceTransform::Enveloped,
+        XmlSecReferenceTransform::Canonicalization(XmlSecCanonicalizationMethod::ExclusiveC14N, Some("xs".into())),
+    ]);
+
+    let keyinfo = info.key_info.expect("Signature should have KeyInfo");
+
+    assert_eq!(keyinfo.key_names, vec!["test-key".to_owned()]);
+    assert_eq!(keyinfo.certificates.len(), 1);
+    assert_eq!(keyinfo.certificates[0][0], 0x30, "Certificate should be DER encoded");
+}
+
+
+#[test]
+fn test_parse_rejects_invalid_structure()
+{
+    let parser = XmlParser::default();
+
+    let missing_value = parser.parse_string(r#"
+        <Signature xmlns="http://www.w3.org/2000/09/xmldsig#">
+          <SignedInfo>
+            <CanonicalizationMethod Algorithm="http://www.w3.org/2001/10/xml-exc-c14n#"/>
+            <SignatureMethod Algorithm="http://www.w3.org/2000/09/xmldsig#rsa-sha1"/>
+            <Reference URI="">
+              <DigestMethod Algorithm="http://www.w3.org/2000/09/xmldsig#sha1"/>
+              <DigestValue/>
+            </Reference>
+          </SignedInfo>
+          <KeyInfo/>
+        </Signature>"#)
+        .expect("Failed to parse test signature");
+
+    let no_reference = parser.parse_string(r#"
+        <Signature xmlns="http://www.w3.org/2000/09/xmldsig#">
+          <SignedInfo>
+            <CanonicalizationMethod Algorithm="http://www.w3.org/2001/10/xml-exc-c14n#"/>
+            <SignatureMethod Algorithm="http://www.w3.org/2000/09/xmldsig#rsa-sha1"/>
+          </SignedInfo>
+          <SignatureValue/>
+        </Signature>"#)
+        .expect("Failed to parse test signature");
+
+    let unknown_algorithm = parser.parse_string(r#"
+        <Signature xmlns="http://www.w3.org/2000/09/xmldsig#">
+          <SignedInfo>
+            <CanonicalizationMethod Algorithm="urn:unknown"/>
+            <SignatureMethod Algorithm="http://www.w3.org/2000/09/xmldsig#rsa-sha1"/>
+            <Reference URI="">
+              <DigestMethod Algorithm="http://www.w3.org/2000/09/xmldsig#sha1"/>
+              <DigestValue/>
+            </Reference>
+          </SignedInfo>
+          <SignatureValue/>
+        </Signature>"#)
+        .expect("Failed to parse test signature");
+
+    let not_dsig = parser.parse_string("<Signature><SignedInfo/><SignatureValue/></Signature>")
+        .expect("Failed to parse test signature");
+
+    for doc in [missing_value, no_reference, unknown_algorithm, not_dsig]
+    {
+        let root = doc.get_root_element().unwrap();
+
+        match XmlSecSignatureInfo::parse(&root)
+        {
+            Err(XmlSecError::MalformedSignature(_)) => (),
+            other                                   => panic!("Expected malformed signature, got {:?}", other),
+        }
+    }
+}
+
+
+fn find_signature(doc: &XmlDocument) -> XmlNode
+{
+    doc.get_root_element()
+        .expect("Document should have a root")
+        .get_child_elements()
+        .into_iter()
+        .find(|n| n.get_name() == "Signature")
+        .expect("Document should hold a signature")
+}
@@ -0,0 +1,371 @@
+//!
+//! Testing of Reading and Writing Keys in KeyInfo
+//!
+use xmlsec::XmlSecKey;
+use xmlsec::XmlSecKeyFormat;
+use xmlsec::XmlSecKeyInfoOptions;
+use xmlsec::XmlSecKeyInfoWriteOptions;
+use xmlsec::XmlSecKeyKind;
+use xmlsec::XmlSecKeysManager;
+use xmlsec::XmlSecError;
+use xmlsec::XmlSecSignatureContext;
+use xmlsec::XmlSecSignatureMethod;
+use xmlsec::XmlSecTemplateBuilder;
+use xmlsec::XmlSecDocumentTemplating;
+
+use libxml::parser::Parser as XmlParser;
+use libxml::tree::Document as XmlDocument;
+use libxml::tree::Node as XmlNode;
+
+
+// uncompressed point of tests/resources/key-ec.pem, as printed by `openssl ec -pubout`
+const EC_PUBLIC_POINT: &str =
+    "BDgPpMXT/0zqzPszfSudujhVXChRrZW6VGde/m6WMoIYQsuVB+kS4VPtjCswT1/o7I0Uzl/697TRZuBrkpvKgoc=";
+
+
+#[test]
+fn test_keyinfo_key_value_and_certificate()
+{
+    let doc = load_keyinfos();
+
+    let key = XmlSecKey::from_keyinfo_node(&find_keyinfo(&doc, "full"), XmlSecKeyInfoOptions::new())
+        .expect("Failed to read key from KeyInfo");
+
+    assert_eq!(key.kind(), Some(XmlSecKeyKind::Rsa));
+    assert!(!key.is_private());
+    assert_eq!(key.get_name(), Some("signer"));
+    assert_eq!(key, public_key());
+
+    let certs = key.certificates()
+        .expect("Failed to inspect certificates");
+
+    assert_eq!(certs.len(), 1);
+    assert_eq!(certs[0].serial, "5A6813AEB733B47220A350C2DE5F027CACE75E45");
+}
+
+
+#[test]
+fn test_keyinfo_certificate_chain()
+{
+    let doc  = load_keyinfos();
+    let node = find_keyinfo(&doc, "chain");
+
+    let key = XmlSecKey::from_keyinfo_node(&node, XmlSecKeyInfoOptions::new())
+        .expect("Failed to read key from KeyInfo");
+
+    assert_eq!(key, public_key(), "Key must be taken from the certificate holding it");
+    assert_eq!(key.get_name(), None);
+
+    let subjects = key.certificates()
+        .expect("Failed to inspect certificates")
+        .into_iter()
+        .map(|c| c.subject)
+        .collect::<Vec<_>>();
+
+    assert_eq!(subjects, ["CN=Test Signer", "CN=Test CA"]);
+
+    // neither certificate is trusted by an empty manager
+    let manager = XmlSecKeysManager::new()
+        .expect("Failed to create keys manager");
+
+    let options = XmlSecKeyInfoOptions::new()
+        .keys_manager(&manager)
+        .verify_certificates(true);
+
+    let untrusted = XmlSecKey::from_keyinfo_node(&node, options)
+        .expect("Failed to read key from KeyInfo");
+
+    assert_eq!(untrusted.kind(), None);
+    assert_eq!(untrusted.certificates().unwrap().len(), 2);
+}
+
+
+#[test]
+fn test_keyinfo_certificate_of_other_key()
+{
+    let doc = load_keyinfos();
+
+    // key value of the signer next to the certificate of its CA
+    assert!(matches!(
+        XmlSecKey::from_keyinfo_node(&find_keyinfo(&doc, "mismatch"), XmlSecKeyInfoOptions::new()),
+        Err(XmlSecError::KeyFormatError(_))
+    ), "Certificates not holding the key value must be rejected");
+}
+
+
+#[test]
+fn test_keyinfo_der_encoded_key_value()
+{
+    let doc = load_keyinfos();
+
+    let key = XmlSecKey::from_keyinfo_node(&find_keyinfo(&doc, "der"), XmlSecKeyInfoOptions::new())
+        .expect("Failed to read key from KeyInfo");
+
+    assert_eq!(key, public_key());
+}
+
+
+#[test]
+fn test_keyinfo_key_name()
+{
+    let doc  = load_keyinfos();
+    let node = find_keyinfo(&doc, "name");
+
+    let named = XmlSecKey::from_keyinfo_node(&node, XmlSecKeyInfoOptions::new())
+        .expect("Failed to read key name from KeyInfo");
+
+    assert_eq!(named.get_name(), Some("signer"));
+    assert_eq!(named.kind(), None);
+
+    let mut key = XmlSecKey::from_file("tests/resources/key.pem", XmlSecKeyFormat::Pem, None)
+        .expect("Failed to properly load key for test");
+
+    key.set_name("signer")
+        .expect("Failed to set key name");
+
+    let mut manager = XmlSecKeysManager::new()
+        .expect("Failed to create keys manager");
+
+    manager.add_key(key)
+        .expect("Failed to add key to manager");
+
+    let found = XmlSecKey::from_keyinfo_node(&node, XmlSecKeyInfoOptions::new().keys_manager(&manager))
+        .expect("Failed to look up key named in KeyInfo");
+
+    assert_eq!(found.get_name(), Some("signer"));
+    assert_eq!(found.kind(), Some(XmlSecKeyKind::Rsa));
+}
+
+
+#[test]
+fn test_keyinfo_rejects_other_nodes()
+{
+    let doc = load_keyinfos();
+    let root = doc.get_root_element().expect("Missing root element");
+
+    assert!(matches!(
+        XmlSecKey::from_keyinfo_node(&root, XmlSecKeyInfoOptions::new()),
+        Err(XmlSecError::KeyFormatError(_))
+    ));
+
+    let empty = XmlParser::default()
+        .parse_string(r#"<KeyInfo xmlns="http://www.w3.org/2000/09/xmldsig#"><KeyName/></KeyInfo>"#)
+        .expect("Failed to parse KeyInfo");
+
+    assert!(matches!(
+        XmlSecKey::from_keyinfo_node(&empty.get_root_element().unwrap(), XmlSecKeyInfoOptions::new()),
+        Err(XmlSecError::KeyFormatError(_))
+    ));
+}
+
+
+#[test]
+fn test_keyinfo_write_round_trip()
+{
+    let key = signing_key();
+
+    let doc = XmlParser::default()
+        .parse_string("<Root/>")
+        .expect("Failed to parse document");
+
+    let root = doc.get_root_element().expect("Missing root element");
+
+    let keyinfo = key.write_keyinfo_in(&root, XmlSecKeyInfoWriteOptions::new())
+        .expect("Failed to write KeyInfo");
+
+    assert_eq!(child_names(&keyinfo), ["KeyName", "KeyValue", "X509Data"]);
+    assert!(doc.to_string().contains("<Modulus>"));
+    assert!(!doc.to_string().contains("PrivateExponent"), "Private key material must not be written");
+
+    let read = XmlSecKey::from_keyinfo_node(&keyinfo, XmlSecKeyInfoOptions::new())
+        .expect("Failed to read back KeyInfo");
+
+    assert_eq!(read, key.to_public().unwrap());
+    assert_eq!(read.get_name(), Some("signer"));
+    assert_eq!(read.certificates_to_der().unwrap(), key.certificates_to_der().unwrap());
+
+    // writing again targets the very same node
+    let again = key.write_keyinfo_in(&root, XmlSecKeyInfoWriteOptions::new().x509data(false))
+        .expect("Failed to write KeyInfo again");
+
+    assert_eq!(again, keyinfo);
+    assert_eq!(root.get_child_elements().len(), 1);
+}
+
+
+#[test]
+fn test_keyinfo_write_ec_key_value()
+{
+    let key = XmlSecKey::from_file("tests/resources/key-ec.pem", XmlSecKeyFormat::Pem, None)
+        .expect("Failed to properly load EC key for test");
+
+    let doc = XmlParser::default()
+        .parse_string(r#"<KeyInfo xmlns="http://www.w3.org/2000/09/xmldsig#"/>"#)
+        .expect("Failed to parse KeyInfo");
+
+    let keyinfo = doc.get_root_element().expect("Missing root element");
+
+    key.write_keyinfo_node(&keyinfo, XmlSecKeyInfoWriteOptions::new())
+        .expect("Failed to write KeyInfo of EC key");
+
+    assert_eq!(child_names(&keyinfo), ["KeyValue"]);
+
+    let keyvalue = &keyinfo.get_child_elements()[0];
+
+    assert_eq!(child_names(keyvalue), ["ECKeyValue"]);
+
+    let ecvalue = &keyvalue.get_child_elements()[0];
+
+    assert_eq!(ecvalue.get_namespace().map(|ns| ns.get_href()).as_deref(), Some("http://www.w3.org/2009/xmldsig11#"));
+    assert_eq!(child_names(ecvalue), ["NamedCurve", "PublicKey"]);
+
+    let children = ecvalue.get_child_elements();
+
+    assert_eq!(children[0].get_property("URI").as_deref(), Some("urn:oid:1.2.840.10045.3.1.7"));
+
+    let point = children[1].get_content()
+        .split_whitespace()
+        .collect::<String>();
+
+    assert_eq!(point, EC_PUBLIC_POINT);
+}
+
+
+#[test]
+fn test_keyinfo_write_existing_node()
+{
+    let key = signing_key();
+
+    let doc = XmlParser::default()
+        .parse_string(r#"<KeyInfo xmlns="http://www.w3.org/2000/09/xmldsig#"><KeyName>given</KeyName></KeyInfo>"#)
+        .expect("Failed to parse KeyInfo");
+
+    let keyinfo = doc.get_root_element().expect("Missing root element");
+
+    let options = XmlSecKeyInfoWriteOptions::new()
+        .keyvalue(false);
+
+    key.write_keyinfo_node(&keyinfo, options)
+        .expect("Failed to write KeyInfo");
+
+    assert_eq!(child_names(&keyinfo), ["KeyName", "X509Data"]);
+    assert_eq!(keyinfo.get_child_elements()[0].get_content(), "given");
+
+    let hmac = XmlSecKey::from_hmac_secret(b"shared secret").unwrap();
+
+    let doc = XmlParser::default()
+        .parse_string(r#"<KeyInfo xmlns="http://www.w3.org/2000/09/xmldsig#"/>"#)
+        .expect("Failed to parse KeyInfo");
+
+    let keyinfo = doc.get_root_element().expect("Missing root element");
+
+    hmac.write_keyinfo_node(&keyinfo, XmlSecKeyInfoWriteOptions::new())
+        .expect("Failed to write KeyInfo");
+
+    assert!(keyinfo.get_child_elements().is_empty(), "Secret keys must not be written");
+
+    let doc  = load_keyinfos();
+    let root = doc.get_root_element().expect("Missing root element");
+
+    assert!(matches!(
+        key.write_keyinfo_node(&root, XmlSecKeyInfoWriteOptions::new()),
+        Err(XmlSecError::KeyExportError(_))
+    ));
+}
+
+
+#[test]
+fn test_keyinfo_write_into_signature()
+{
+    let doc = XmlParser::default()
+        .parse_string("<Data>Hello</Data>")
+        .expect("Failed to parse document to sign");
+
+    doc.template()
+        .signature(XmlSecSignatureMethod::RsaSha256)
+        .uri("")
+        .done()
+        .expect("Failed to build signature template");
+
+    let signature = doc.get_root_element()
+        .expect("Missing root element")
+        .get_child_elements()
+        .into_iter()
+        .find(|n| n.get_name() == "Signature")
+        .expect("Missing signature");
+
+    let key = signing_key();
+
+    key.write_keyinfo_in(&signature, XmlSecKeyInfoWriteOptions::new())
+        .expect("Failed to write KeyInfo");
+
+    assert_eq!(child_names(&signature), ["SignedInfo", "SignatureValue", "KeyInfo"]);
+
+    let mut signer = XmlSecSignatureContext::new()
+        .expect("Failed to create signature context");
+    signer.insert_key(key);
+
+    signer.sign_document(&doc)
+        .expect("Failed to sign document");
+
+    let keyinfo = signature.get_child_elements().pop().expect("Missing KeyInfo");
+
+    let signer_key = XmlSecKey::from_keyinfo_node(&keyinfo, XmlSecKeyInfoOptions::new())
+        .expect("Failed to read signer key");
+
+    let mut verifier = XmlSecSignatureContext::new()
+        .expect("Failed to create signature context");
+    verifier.insert_key(signer_key);
+
+    assert!(verifier.verify_document(&doc).expect("Failed to verify document"));
+}
+
+
+fn load_keyinfos() -> XmlDocument
+{
+    XmlParser::default()
+        .parse_file("tests/resources/keyinfo.xml")
+        .expect("Failed to load KeyInfo samples")
+}
+
+
+fn find_keyinfo(doc: &XmlDocument, id: &str) -> XmlNode
+{
+    doc.get_root_element()
+        .expect("Missing root element")
+        .get_child_elements()
+        .into_iter()
+        .find(|n| n.get_property("Id").as_deref() == Some(id))
+        .unwrap_or_else(|| panic!("Missing KeyInfo {}", id))
+}
+
+
+fn public_key() -> XmlSecKey
+{
+    XmlSecKey::from_file("tests/resources/key-pub.pem", XmlSecKeyFormat::Pem, None)
+        .expect("Failed to properly load public key for test")
+}
+
+
+fn signing_key() -> XmlSecKey
+{
+    let mut key = XmlSecKey::from_file("tests/resources/key.pem", XmlSecKeyFormat::Pem, None)
+        .expect("Failed to properly load key for test");
+
+    key.load_cert_chain_from_file("tests/resources/chain.pem", XmlSecKeyFormat::Pem, None)
+        .expect("Failed to load certificate chain");
+
+    key.set_name("signer")
+        .expect("Failed to set key name");
+
+    key
+}
+
+
+fn child_names(node: &XmlNode) -> Vec<String>
+{
+    node.get_child_elements()
+        .into_iter()
+        .map(|n| n.get_name())
+        .collect()
+}
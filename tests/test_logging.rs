@@ -0,0 +1,117 @@
+//!
+//! Testing of XmlSec Error Logging
+//!
+#![cfg(feature = "log")]
+
+use xmlsec::XmlSecKey;
+use xmlsec::XmlSecKeyFormat;
+use xmlsec::XmlSecSignatureContext;
+
+use libxml::parser::Parser as XmlParser;
+
+use log::Level;
+use log::Log;
+use log::Metadata;
+use log::Record;
+
+use std::sync::Mutex;
+
+
+#[derive(Debug)]
+struct Captured
+{
+    level:    Level,
+    target:   String,
+    function: String,
+    object:   String,
+    subject:  String,
+    reason:   String,
+}
+
+
+struct CapturingLogger
+{
+    records: Mutex<Vec<Captured>>,
+}
+
+
+impl Log for CapturingLogger
+{
+    fn enabled(&self, _: &Metadata) -> bool
+    {
+        true
+    }
+
+    fn log(&self, record: &Record)
+    {
+        let field = |key: &str| record.key_values()
+            .get(key.into())
+            .map(|v| v.to_string())
+            .unwrap_or_default();
+
+        self.records.lock().unwrap().push(Captured {
+            level:    record.level(),
+            target:   record.target().to_owned(),
+            function: field("function"),
+            object:   field("object"),
+            subject:  field("subject"),
+            reason:   field("reason"),
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+
+static LOGGER: CapturingLogger = CapturingLogger {records: Mutex::new(Vec::new())};
+
+
+#[test]
+fn test_verification_failure_is_logged()
+{
+    log::set_logger(&LOGGER).expect("Failed to install test logger");
+    log::set_max_level(log::LevelFilter::Trace);
+
+    let signed = std::fs::read_to_string("tests/resources/sign1-res.xml")
+        .expect("Failed to read signed document");
+
+    let doc = XmlParser::default()
+        .parse_string(signed.replace("Hello", "Jello"))
+        .expect("Failed to parse tampered document");
+
+    let key = XmlSecKey::from_file("tests/resources/key.pem", XmlSecKeyFormat::Pem, None)
+        .expect("Failed to properly load key for test");
+
+    let mut ctx = XmlSecSignatureContext::new()
+        .expect("Failed to create signature context");
+    ctx.insert_key(key);
+
+    let valid = ctx.verify_document(&doc)
+        .expect("Failed to verify document");
+
+    assert!(!valid, "Tampered document should not verify");
+
+    let records = LOGGER.records.lock().unwrap();
+
+    let mismatch = records.iter()
+        .find(|r| r.function == "xmlSecOpenSSLEvpDigestVerify")
+        .unwrap_or_else(|| panic!("Digest mismatch should have been logged, got {:?}", records));
+
+    assert_eq!(mismatch.level, Level::Warn);
+    assert_eq!(mismatch.target, "xmlsec");
+    assert_eq!(mismatch.object, "sha1");
+    assert_eq!(mismatch.reason, "12");
+
+    drop(records);
+
+    assert!(XmlSecKey::from_memory(b"not a key", XmlSecKeyFormat::Pem, None).is_err());
+
+    let records = LOGGER.records.lock().unwrap();
+
+    let failure = records.iter()
+        .find(|r| r.function == "xmlSecOpenSSLAppKeyLoadMemory")
+        .unwrap_or_else(|| panic!("Key load failure should have been logged, got {:?}", records));
+
+    assert_eq!(failure.level, Level::Error);
+    assert_eq!(failure.subject, "xmlSecOpenSSLAppKeyLoadBIO");
+}
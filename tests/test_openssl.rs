@@ -0,0 +1,144 @@
+//!
+//! Testing of Interoperability with the OpenSSL Crate
+//!
+#![cfg(feature = "openssl")]
+
+use xmlsec::XmlSecKey;
+use xmlsec::XmlSecKeyFormat;
+use xmlsec::XmlSecKeysManager;
+use xmlsec::XmlSecSignatureContext;
+
+use libxml::parser::Parser as XmlParser;
+
+use openssl::error::ErrorStack;
+use openssl::pkey::PKey;
+use openssl::pkey::Private;
+use openssl::x509::X509;
+use openssl::x509::store::X509StoreBuilder;
+
+
+#[test]
+fn test_key_from_and_to_pkey()
+{
+    let pem = std::fs::read("tests/resources/key.pem")
+        .expect("Failed to read key for test");
+
+    let pkey = PKey::private_key_from_pem(&pem)
+        .expect("Failed to parse key with openssl");
+
+    let key = XmlSecKey::try_from(&*pkey)
+        .expect("Failed to convert openssl key");
+
+    let expected = XmlSecKey::from_memory(&pem, XmlSecKeyFormat::Pem, None)
+        .expect("Failed to properly load key for test");
+
+    assert_eq!(
+        key.public_key_to_der().unwrap(),
+        expected.public_key_to_der().unwrap()
+    );
+
+    drop(pkey);  // the key material is shared, not tied to the original
+
+    let back: PKey<Private> = (&key).try_into()
+        .expect("Failed to convert key back to openssl");
+
+    assert_eq!(back.public_key_to_der().unwrap(), key.public_key_to_der().unwrap());
+
+    let public = key.to_public_pkey()
+        .expect("Failed to convert public key to openssl");
+
+    assert!(public.public_eq(&back));
+
+    drop(key);
+
+    assert_eq!(back.bits(), 1024, "Converted key must outlive the original");
+
+    let hmac = XmlSecKey::from_hmac_secret(b"shared secret")
+        .expect("Failed to create HMAC key");
+
+    assert!(hmac.to_pkey().is_err(), "Symmetric keys have no OpenSSL key pair");
+}
+
+
+#[test]
+fn test_key_x509_certificates()
+{
+    let cert = X509::from_pem(&std::fs::read("tests/resources/key.crt").unwrap())
+        .expect("Failed to parse certificate with openssl");
+
+    let key = XmlSecKey::from_file("tests/resources/key.pem", XmlSecKeyFormat::Pem, None)
+        .expect("Failed to properly load key for test");
+
+    key.add_x509(&cert)
+        .expect("Failed to attach openssl certificate");
+
+    let certs = key.x509_certificates()
+        .expect("Failed to reference attached certificates");
+
+    assert_eq!(certs.len(), 1);
+    assert_eq!(certs[0].to_der().unwrap(), cert.to_der().unwrap());
+
+    drop(key);
+
+    assert!(certs[0].subject_name().entries().count() > 0, "Certificates must outlive the key");
+}
+
+
+#[test]
+fn test_keys_manager_from_x509_store()
+{
+    let cert = X509::from_pem(&std::fs::read("tests/resources/key.crt").unwrap())
+        .expect("Failed to parse certificate with openssl");
+
+    let mut builder = X509StoreBuilder::new()
+        .expect("Failed to create openssl certificate store");
+
+    builder.add_cert(cert)
+        .expect("Failed to add certificate to openssl store");
+
+    let store = builder.build();
+
+    let mut manager = XmlSecKeysManager::new()
+        .expect("Failed to create keys manager");
+
+    manager.add_x509_store(&store)
+        .expect("Failed to add openssl store to keys manager");
+
+    let pem = std::fs::read("tests/resources/key.pem").unwrap();
+    let key = XmlSecKey::from_pkey(&PKey::private_key_from_pem(&pem).unwrap())
+        .expect("Failed to convert openssl key");
+
+    manager.add_key(key)
+        .expect("Failed to add key to manager");
+
+    let ctx = XmlSecSignatureContext::with_keys_manager(manager)
+        .expect("Failed to create signature context");
+
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign1-res.xml")
+        .expect("Failed to load signature for verification testing");
+
+    assert!(ctx.verify_document(&doc).expect("Failed to verify document"));
+}
+
+
+#[test]
+fn test_openssl_error_queue_preserved()
+{
+    let failure = PKey::private_key_from_pem(b"not a key")
+        .expect_err("Garbage should not parse as key");
+
+    let queued = failure.errors()[0].clone();
+
+    queued.put();
+
+    assert!(XmlSecKey::from_memory(b"not a key either", XmlSecKeyFormat::Pem, None).is_err());
+
+    let left = ErrorStack::get();
+
+    assert_eq!(
+        left.errors().first().map(|e| e.code()),
+        Some(queued.code()),
+        "Errors queued beforehand must be queued again"
+    );
+}
@@ -3,10 +3,25 @@
 //!
 use xmlsec::XmlSecTemplateBuilder;
 use xmlsec::XmlSecDocumentTemplating;
+use xmlsec::XmlSecNodeTemplating;
+use xmlsec::XmlSecTemplatePlacement;
+use xmlsec::XmlSecTemplateReference;
+use xmlsec::XmlSecTemplateObject;
+use xmlsec::XmlSecTemplateObjectContent;
 use xmlsec::XmlSecCanonicalizationMethod;
 use xmlsec::XmlSecSignatureMethod;
 
+use xmlsec::XmlSecKey;
+use xmlsec::XmlSecKeyFormat;
+use xmlsec::XmlSecSignatureContext;
+use xmlsec::XmlSecError;
+
 use libxml::parser::Parser as XmlParser;
+use libxml::tree::Document as XmlDocument;
+use libxml::tree::Node as XmlNode;
+
+
+const DSIG_NS: &str = "http://www.w3.org/2000/09/xmldsig#";
 
 
 #[test]
@@ -65,3 +80,636 @@ fn test_template_creation_with_ns_prefix()
 
     assert_eq!(doc.to_string(), reference);
 }
+
+#[test]
+fn test_template_creation_with_inclusive_namespaces()
+{
+    // load document
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    // add signature node structure
+    doc.template()
+        .canonicalization(XmlSecCanonicalizationMethod::ExclusiveC14N)
+        .inclusive_namespaces("xs xsi")
+        .signature(XmlSecSignatureMethod::RsaSha1)
+        .reference_canonicalization(XmlSecCanonicalizationMethod::ExclusiveC14N)
+        .reference_inclusive_namespaces("xs")
+        .uri("ReferencedID")
+        .done()
+        .expect("Failed to build and attach signature");
+
+    // compare template results
+    let reference = String::from_utf8(
+        include_bytes!("./resources/sign2-tmpl-incl-ns.xml").to_vec()
+    ).unwrap();
+
+    assert_eq!(doc.to_string(), reference);
+}
+
+#[test]
+fn test_template_inclusive_namespaces_require_exclusive_c14n()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    let result = doc.template()
+        .canonicalization(XmlSecCanonicalizationMethod::InclusiveC14N)
+        .inclusive_namespaces("xs")
+        .done();
+
+    assert!(result.is_err(), "Inclusive namespaces must be refused for inclusive canonicalization");
+}
+
+#[test]
+fn test_template_inclusive_namespaces_sign_verify()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    doc.template()
+        .canonicalization(XmlSecCanonicalizationMethod::ExclusiveC14N)
+        .inclusive_namespaces("xs")
+        .signature(XmlSecSignatureMethod::RsaSha1)
+        .reference_canonicalization(XmlSecCanonicalizationMethod::ExclusiveC14N)
+        .reference_inclusive_namespaces("xs")
+        .done()
+        .expect("Failed to build and attach signature");
+
+    sign_and_verify(&doc);
+}
+
+#[test]
+fn test_node_template_placed_after_node()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    let root = doc.get_root_element()
+        .expect("Template document should have a root");
+
+    let data = root.get_first_element_child()
+        .expect("Template document should have a <Data/> node");
+
+    root.template()
+        .uri("ReferencedID")
+        .placement(XmlSecTemplatePlacement::After(data))
+        .done()
+        .expect("Failed to build and attach signature");
+
+    let reference = String::from_utf8(
+        include_bytes!("./resources/sign2-tmpl-after-data.xml").to_vec()
+    ).unwrap();
+
+    assert_eq!(doc.to_string(), reference);
+}
+
+#[test]
+fn test_node_template_placed_before_node()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    let root = doc.get_root_element()
+        .expect("Template document should have a root");
+
+    let data = root.get_first_element_child()
+        .expect("Template document should have a <Data/> node");
+
+    root.template()
+        .uri("ReferencedID")
+        .placement(XmlSecTemplatePlacement::Before(data))
+        .done()
+        .expect("Failed to build and attach signature");
+
+    let children = root.get_child_elements()
+        .iter()
+        .map(|c| c.get_name())
+        .collect::<Vec<_>>();
+
+    assert_eq!(children, vec!["Signature", "Data"]);
+
+    let result = doc.template()
+        .placement(XmlSecTemplatePlacement::Before(root))
+        .done();
+
+    assert!(result.is_err(), "Placement next to the root element should fail");
+}
+
+#[test]
+fn test_template_placed_first_child()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    doc.template()
+        .uri("ReferencedID")
+        .placement(XmlSecTemplatePlacement::FirstChild)
+        .done()
+        .expect("Failed to build and attach signature");
+
+    let root = doc.get_root_element()
+        .expect("Template document should have a root");
+
+    let children = root.get_child_elements()
+        .iter()
+        .map(|c| (c.get_name(), namespace(c)))
+        .collect::<Vec<_>>();
+
+    assert_eq!(children, vec![
+        ("Signature".to_owned(), Some(DSIG_NS.to_owned())),
+        ("Data".to_owned(), Some("urn:envelope".to_owned())),
+    ]);
+
+    assert_eq!(
+        root.get_first_child().map(|c| c.get_name()),
+        Some("Signature".to_owned()),
+        "Signature should precede even the leading whitespace"
+    );
+}
+
+#[test]
+fn test_template_placed_by_xpath()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    doc.template()
+        .uri("ReferencedID")
+        .placement(XmlSecTemplatePlacement::XPath(
+            "/env:Envelope/env:Data".into(),
+            vec![("env".into(), "urn:envelope".into())]
+        ))
+        .done()
+        .expect("Failed to build and attach signature");
+
+    let reference = String::from_utf8(
+        include_bytes!("./resources/sign2-tmpl-in-data.xml").to_vec()
+    ).unwrap();
+
+    assert_eq!(doc.to_string(), reference);
+}
+
+#[test]
+fn test_template_placement_xpath_not_found()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    let result = doc.template()
+        .placement(XmlSecTemplatePlacement::XPath("//Missing".into(), Vec::new()))
+        .done();
+
+    assert!(result.is_err(), "Placement into a non existing node should fail");
+}
+
+#[test]
+fn test_template_placement_xpath_bad_namespace()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    let result = doc.template()
+        .placement(XmlSecTemplatePlacement::XPath(
+            "/env:Envelope/env:Data".into(),
+            vec![("e\0nv".into(), "urn:envelope".into())]
+        ))
+        .done();
+
+    assert!(matches!(result, Err(XmlSecError::InvalidInput(_))), "Got {:?}", result);
+}
+
+
+#[test]
+fn test_template_creation_with_ids()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    doc.template()
+        .signature_id("sig")
+        .signed_info_id("sig-signedinfo")
+        .signature_value_id("sig-value")
+        .keyinfo_id("sig-keyinfo")
+        .reference_id("sig-ref")
+        .reference_type("http://www.w3.org/2000/09/xmldsig#Object")
+        .uri("ReferencedID")
+        .done()
+        .expect("Failed to build and attach signature");
+
+    let reference = String::from_utf8(
+        include_bytes!("./resources/sign2-tmpl-ids.xml").to_vec()
+    ).unwrap();
+
+    assert_eq!(doc.to_string(), reference);
+}
+
+#[test]
+fn test_template_ids_referenced_in_same_pass()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    // the reference points at the KeyInfo of the very same signature, which only resolves if its Id got registered
+    doc.template()
+        .signature_id("sig")
+        .keyinfo_id("sig-keyinfo")
+        .keyname(true)
+        .uri("#sig-keyinfo")
+        .done()
+        .expect("Failed to build and attach signature");
+
+    let key = XmlSecKey::from_file("tests/resources/key.pem", XmlSecKeyFormat::Pem, None)
+        .expect("Failed to properly load key for test");
+
+    let mut ctx = XmlSecSignatureContext::new()
+        .expect("Failed to create signature context");
+    ctx.insert_key(key);
+
+    ctx.sign_document(&doc)
+        .expect("Failed to sign document referencing its own KeyInfo");
+}
+
+#[test]
+fn test_template_duplicate_ids()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    let result = doc.template()
+        .signature_id("dup")
+        .keyinfo_id("dup")
+        .done();
+
+    assert!(result.is_err(), "Duplicate Ids must be refused");
+}
+
+#[test]
+fn test_template_creation_with_rich_keyinfo()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    doc.template()
+        .keyname_text("partner-key")
+        .x509_issuer_serial(true)
+        .x509_subject_name(true)
+        .x509_ski(true)
+        .x509_certificate(true)
+        .x509_crl(true)
+        .x509_digest(XmlSecSignatureMethod::Sha256)
+        .retrieval_method("#partner-cert", Some("http://www.w3.org/2000/09/xmldsig#X509Data"))
+        .keyinfo_reference("#partner-keyinfo")
+        .uri("ReferencedID")
+        .done()
+        .expect("Failed to build and attach signature");
+
+    let reference = String::from_utf8(
+        include_bytes!("./resources/sign2-tmpl-keyinfo.xml").to_vec()
+    ).unwrap();
+
+    assert_eq!(doc.to_string(), reference);
+}
+
+#[test]
+fn test_template_x509_digest_requires_digest_method()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    let result = doc.template()
+        .x509_digest(XmlSecSignatureMethod::RsaSha256)
+        .done();
+
+    assert!(matches!(result, Err(XmlSecError::InvalidInput(_))), "Got {:?}", result);
+    assert!(!doc.to_string().contains("Signature"), "No template should have been attached");
+}
+
+#[test]
+fn test_template_x509data_filled_by_signing()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    doc.template()
+        .x509_certificate(true)
+        .x509_subject_name(true)
+        .x509_issuer_serial(true)
+        .x509_digest(XmlSecSignatureMethod::Sha256)
+        .done()
+        .expect("Failed to build and attach signature");
+
+    let key = XmlSecKey::from_file("tests/resources/key.pem", XmlSecKeyFormat::Pem, None)
+        .expect("Failed to properly load key for test");
+
+    key.load_cert_from_file("tests/resources/key.crt", XmlSecKeyFormat::Pem)
+        .expect("Failed to properly load key certificate for test");
+
+    let mut ctx = XmlSecSignatureContext::new()
+        .expect("Failed to create signature context");
+    ctx.insert_key(key);
+
+    ctx.sign_document(&doc)
+        .expect("Failed to sign document");
+
+    let signed = doc.to_string();
+
+    assert!(!signed.contains("<X509Certificate/>"), "Certificate should have been filled in by signing");
+    assert!(signed.contains("<X509IssuerName>"), "Issuer should have been filled in by signing");
+    assert!(!signed.contains("<X509SubjectName/>"), "Subject name should have been filled in by signing");
+
+    // SHA-256 of tests/resources/key.crt in DER, as printed by `openssl dgst -sha256 -binary | base64`
+    assert!(
+        signed.contains("pMS4Una2l+mtQD/jvhmku3AWMkfuCWQAbk38mouwaJU=</X509Digest>"),
+        "Digest of the certificate should have been filled in by signing"
+    );
+}
+
+
+#[test]
+fn test_template_enveloping_xml_object()
+{
+    let parser = XmlParser::default();
+
+    let payload = parser.parse_string("<Payload xmlns=\"urn:example\"><Amount>42</Amount></Payload>")
+        .expect("Could not parse payload");
+
+    let doc = XmlDocument::new()
+        .expect("Could not create empty document");
+
+    doc.template()
+        .uri("#payload")
+        .enveloped(false)
+        .reference_canonicalization(XmlSecCanonicalizationMethod::ExclusiveC14N)
+        .object(
+            XmlSecTemplateObject::new(XmlSecTemplateObjectContent::Xml(payload.get_root_element().unwrap()))
+                .id("payload")
+        )
+        .done()
+        .expect("Failed to build enveloping signature");
+
+    let root = doc.get_root_element()
+        .expect("Signature should be the document root");
+
+    assert_eq!(root.get_name(), "Signature");
+    assert_eq!(child_names(&root), ["SignedInfo", "SignatureValue", "KeyInfo", "Object"]);
+
+    let transforms = child(&child(&child(&root, "SignedInfo"), "Reference"), "Transforms")
+        .get_child_elements()
+        .iter()
+        .map(|t| t.get_property("Algorithm").unwrap_or_default())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        transforms,
+        ["http://www.w3.org/2001/10/xml-exc-c14n#"],
+        "Enveloping reference must not use the enveloped transform"
+    );
+
+    let object = child(&root, "Object");
+
+    assert_eq!(object.get_property("Id").as_deref(), Some("payload"));
+    assert_eq!(child_names(&object), ["Payload"]);
+
+    let copied = child(&object, "Payload");
+
+    assert_eq!(namespace(&copied).as_deref(), Some("urn:example"));
+    assert_eq!(child(&copied, "Amount").get_content(), "42");
+
+    sign_and_verify(&doc);
+}
+
+#[test]
+fn test_template_enveloping_binary_object()
+{
+    let doc = XmlDocument::new()
+        .expect("Could not create empty document");
+
+    doc.template()
+        .uri("#data")
+        .enveloped(false)
+        .object(
+            XmlSecTemplateObject::new(XmlSecTemplateObjectContent::Binary(b"Hello world".to_vec()))
+                .id("data")
+                .mime_type("text/plain")
+        )
+        .done()
+        .expect("Failed to build enveloping signature");
+
+    let root = doc.get_root_element()
+        .expect("Signature should be the document root");
+
+    assert_eq!(child_names(&child(&child(&root, "SignedInfo"), "Reference")), ["DigestMethod", "DigestValue"]);
+
+    let object = child(&root, "Object");
+
+    assert_eq!(object.get_property("Id").as_deref(), Some("data"));
+    assert_eq!(object.get_property("MimeType").as_deref(), Some("text/plain"));
+    assert_eq!(object.get_property("Encoding").as_deref(), Some("http://www.w3.org/2000/09/xmldsig#base64"));
+    assert_eq!(object.get_content(), "SGVsbG8gd29ybGQ=");
+
+    sign_and_verify(&doc);
+}
+
+#[test]
+fn test_template_object_decoded_reference()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    doc.template()
+        .reference(XmlSecTemplateReference::new("#data").base64(true))
+        .object(
+            XmlSecTemplateObject::new(XmlSecTemplateObjectContent::Binary(b"Hello world".to_vec()))
+                .id("data")
+        )
+        .done()
+        .expect("Failed to build and attach signature");
+
+    let signature = child(&doc.get_root_element().expect("Template document should have a root"), "Signature");
+
+    let decoded = child(&signature, "SignedInfo")
+        .get_child_elements()
+        .into_iter()
+        .find(|r| r.get_property("URI").as_deref() == Some("#data"))
+        .expect("Reference to the object should have been added");
+
+    let transforms = child(&decoded, "Transforms")
+        .get_child_elements()
+        .iter()
+        .map(|t| t.get_property("Algorithm").unwrap_or_default())
+        .collect::<Vec<_>>();
+
+    assert_eq!(transforms, ["http://www.w3.org/2000/09/xmldsig#base64"]);
+
+    sign_and_verify(&doc);
+}
+
+#[test]
+fn test_template_manifest_and_properties_objects()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    let property = parser.parse_string("<Timestamp xmlns=\"urn:example\">2024-01-01T00:00:00Z</Timestamp>")
+        .expect("Could not parse property");
+
+    doc.template()
+        .signature_id("sig")
+        .reference(
+            XmlSecTemplateReference::new("#manifest")
+                .reference_type("http://www.w3.org/2000/09/xmldsig#Manifest")
+                .digest(XmlSecSignatureMethod::Sha256)
+        )
+        .reference(XmlSecTemplateReference::new("#props"))
+        .object(
+            XmlSecTemplateObject::new(XmlSecTemplateObjectContent::Manifest(
+                Some("manifest".into()),
+                vec![XmlSecTemplateReference::new("").enveloped(true)],
+            ))
+        )
+        .object(
+            XmlSecTemplateObject::new(XmlSecTemplateObjectContent::SignatureProperties(
+                Some("props".into()),
+                vec![("#sig".into(), property.get_root_element().unwrap())],
+            ))
+        )
+        .done()
+        .expect("Failed to build and attach signature");
+
+    let signature = child(&doc.get_root_element().expect("Template document should have a root"), "Signature");
+
+    let objects = signature.get_child_elements()
+        .into_iter()
+        .filter(|n| n.get_name() == "Object")
+        .collect::<Vec<_>>();
+
+    assert_eq!(objects.len(), 2);
+
+    let manifest = child(&objects[0], "Manifest");
+
+    assert_eq!(manifest.get_property("Id").as_deref(), Some("manifest"));
+    assert_eq!(child_names(&manifest), ["Reference"]);
+    assert_eq!(child(&manifest, "Reference").get_property("URI").as_deref(), Some(""));
+
+    let properties = child(&objects[1], "SignatureProperties");
+
+    assert_eq!(properties.get_property("Id").as_deref(), Some("props"));
+    assert_eq!(child_names(&properties), ["SignatureProperty"]);
+
+    let property = child(&properties, "SignatureProperty");
+
+    assert_eq!(property.get_property("Target").as_deref(), Some("#sig"));
+    assert_eq!(namespace(&child(&property, "Timestamp")).as_deref(), Some("urn:example"));
+
+    sign_and_verify(&doc);
+}
+
+#[test]
+fn test_template_rejects_interior_nul()
+{
+    let parser = XmlParser::default();
+
+    let doc = parser.parse_file("tests/resources/sign2-doc.xml")
+        .expect("Could not load template document");
+
+    let result = doc.template()
+        .keyname_text("key\0name")
+        .done();
+
+    assert!(matches!(result, Err(XmlSecError::InvalidInput(_))), "Got {:?}", result);
+
+    let result = doc.template()
+        .reference(XmlSecTemplateReference::new("#da\0ta"))
+        .done();
+
+    assert!(matches!(result, Err(XmlSecError::InvalidInput(_))), "Got {:?}", result);
+
+    let root = doc.get_root_element()
+        .expect("Template document should have a root");
+
+    assert_eq!(child_names(&root), ["Data"], "No template should have been attached");
+}
+
+
+fn namespace(node: &XmlNode) -> Option<String>
+{
+    node.get_namespace().map(|ns| ns.get_href())
+}
+
+/// Signs and verifies in separate contexts, as xmlsec contexts only ever process a single signature.
+fn sign_and_verify(doc: &XmlDocument)
+{
+    let mut signer = XmlSecSignatureContext::new()
+        .expect("Failed to create signature context");
+    signer.insert_key(load_test_key());
+
+    signer.sign_document(doc)
+        .expect("Failed to sign document");
+
+    let mut verifier = XmlSecSignatureContext::new()
+        .expect("Failed to create signature context");
+    verifier.insert_key(load_test_key());
+
+    let valid = verifier.verify_document(doc)
+        .expect("Failed to verify document");
+
+    assert!(valid, "Signature should verify");
+}
+
+fn load_test_key() -> XmlSecKey
+{
+    XmlSecKey::from_file("tests/resources/key.pem", XmlSecKeyFormat::Pem, None)
+        .expect("Failed to properly load key for test")
+}
+
+
+fn child(node: &XmlNode, name: &str) -> XmlNode
+{
+    node.get_child_elements()
+        .into_iter()
+        .find(|n| n.get_name() == name)
+        .unwrap_or_else(|| panic!("Missing <{}/> below <{}/>", name, node.get_name()))
+}
+
+fn child_names(node: &XmlNode) -> Vec<String>
+{
+    node.get_child_elements()
+        .into_iter()
+        .map(|n| n.get_name())
+        .collect()
+}
@@ -0,0 +1,465 @@
+//!
+//! Testing of Standalone Transforms
+//!
+use xmlsec::XmlSecCanonicalize;
+use xmlsec::XmlSecCanonicalizationMethod;
+use xmlsec::XmlSecDocumentExt;
+use xmlsec::XmlSecTransformContext;
+use xmlsec::XmlSecCustomTransform;
+use xmlsec::XmlSecCustomTransformId;
+use xmlsec::XmlSecError;
+use xmlsec::XmlSecSignatureMethod;
+use xmlsec::XmlSecSignatureContext;
+use xmlsec::XmlSecKey;
+use xmlsec::XmlSecKeyFormat;
+use xmlsec::XmlSecResult;
+
+use xmlsec::template::preamble::*;
+
+use libxml::parser::Parser as XmlParser;
+
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+
+const DOCUMENT: &str = r#"<!-- head --><a:r xmlns:a="urn:a" xmlns:b="urn:b"><a:x  z='1'   y='2'><!-- in --><b:y/></a:x><q/></a:r>"#;
+
+
+#[test]
+fn test_canonicalize_document()
+{
+    let doc = XmlParser::default()
+        .parse_string(DOCUMENT)
+        .expect("Failed to parse document");
+
+    let canonical = doc.canonicalize(XmlSecCanonicalizationMethod::InclusiveC14N, None)
+        .expect("Failed to canonicalize document");
+
+    assert_eq!(
+        String::from_utf8(canonical).unwrap(),
+        r#"<a:r xmlns:a="urn:a" xmlns:b="urn:b"><a:x y="2" z="1"><b:y></b:y></a:x><q></q></a:r>"#
+    );
+
+    let canonical = doc.canonicalize(XmlSecCanonicalizationMethod::InclusiveC14NWithComments, None)
+        .expect("Failed to canonicalize document with comments");
+
+    assert_eq!(
+        String::from_utf8(canonical).unwrap(),
+        "<!-- head -->\n<a:r xmlns:a=\"urn:a\" xmlns:b=\"urn:b\"><a:x y=\"2\" z=\"1\"><!-- in --><b:y></b:y></a:x><q></q></a:r>"
+    );
+}
+
+
+#[test]
+fn test_canonicalize_node()
+{
+    let doc = XmlParser::default()
+        .parse_string(DOCUMENT)
+        .expect("Failed to parse document");
+
+    let node = doc.get_root_element()
+        .and_then(|r| r.get_first_element_child())
+        .expect("Document should have a child element");
+
+    let exclusive = node.canonicalize(XmlSecCanonicalizationMethod::ExclusiveC14N, None)
+        .expect("Failed to canonicalize node");
+
+    assert_eq!(
+        String::from_utf8(exclusive).unwrap(),
+        r#"<a:x xmlns:a="urn:a" y="2" z="1"><b:y xmlns:b="urn:b"></b:y></a:x>"#
+    );
+
+    let inclusive_b = node.canonicalize(XmlSecCanonicalizationMethod::ExclusiveC14N, Some("b"))
+        .expect("Failed to canonicalize node with inclusive namespaces");
+
+    assert_eq!(
+        String::from_utf8(inclusive_b).unwrap(),
+        r#"<a:x xmlns:a="urn:a" xmlns:b="urn:b" y="2" z="1"><b:y></b:y></a:x>"#
+    );
+}
+
+
+#[test]
+fn test_canonicalize_xpath_selection()
+{
+    let doc = XmlParser::default()
+        .parse_string(DOCUMENT)
+        .expect("Failed to parse document");
+
+    let canonical = doc.canonicalize_xpath(
+        "//a:x | //a:x/@*",
+        Some(&[("a", "urn:a")]),
+        XmlSecCanonicalizationMethod::InclusiveC14N,
+        None,
+    ).expect("Failed to canonicalize selection");
+
+    assert_eq!(String::from_utf8(canonical).unwrap(), r#"<a:x y="2" z="1"></a:x>"#);
+
+    assert!(
+        doc.canonicalize_xpath("//missing", None, XmlSecCanonicalizationMethod::InclusiveC14N, None).is_err(),
+        "Empty selections should be refused"
+    );
+}
+
+
+#[test]
+fn test_canonicalize_inclusive_prefixes_require_exclusive()
+{
+    let doc = XmlParser::default()
+        .parse_string(DOCUMENT)
+        .expect("Failed to parse document");
+
+    let result = doc.canonicalize(XmlSecCanonicalizationMethod::InclusiveC14N, Some("a"));
+
+    assert!(result.is_err(), "Inclusive namespaces must be refused for inclusive canonicalization");
+}
+
+
+#[test]
+fn test_transform_context_base64_then_digest()
+{
+    let mut ctx = XmlSecTransformContext::new()
+        .expect("Failed to create transform context");
+
+    ctx.append_base64_decode().expect("Failed to append base64 decoding");
+    ctx.append_digest(XmlSecSignatureMethod::Sha1).expect("Failed to append digest");
+
+    ctx.binary_execute(b"SGVsbG8gd29ybGQ=")
+        .expect("Failed to execute transforms");
+
+    assert_eq!(ctx.result().map(hex), Some("7b502c3a1f48c8609ae212cdfb639dee39673f5e".to_owned()));
+
+    assert!(ctx.binary_execute(b"SGVsbG8gd29ybGQ=").is_err(), "Contexts execute only once");
+}
+
+
+#[test]
+fn test_transform_context_xml_digest()
+{
+    let doc = XmlParser::default()
+        .parse_string("<a><!-- dropped --><b/></a>")
+        .expect("Failed to parse document");
+
+    let mut ctx = XmlSecTransformContext::new()
+        .expect("Failed to create transform context");
+
+    ctx.append_digest(XmlSecSignatureMethod::Sha1).expect("Failed to append digest");
+
+    ctx.xml_execute_document(&doc)
+        .expect("Failed to execute transforms");
+
+    // digest of implicitly canonicalized "<a><b></b></a>"
+    assert_eq!(ctx.result().map(hex), Some("a6c86fa3661f5de511b96c848e50f83f6eb440d8".to_owned()));
+}
+
+
+#[test]
+fn test_transform_context_c14n_then_sign()
+{
+    let doc = XmlParser::default()
+        .parse_string("<a><!-- dropped --><b/></a>")
+        .expect("Failed to parse document");
+
+    let key = XmlSecKey::from_file("tests/resources/key.pem", XmlSecKeyFormat::Pem, None)
+        .expect("Failed to properly load key for test");
+
+    let mut ctx = XmlSecTransformContext::new()
+        .expect("Failed to create transform context");
+
+    ctx.append_canonicalization(XmlSecCanonicalizationMethod::ExclusiveC14N, None).expect("Failed to append c14n");
+    ctx.append_signature(XmlSecSignatureMethod::RsaSha1, &key).expect("Failed to append signature");
+    ctx.append_base64_encode().expect("Failed to append base64 encoding");
+
+    ctx.xml_execute_document(&doc)
+        .expect("Failed to execute transforms");
+
+    let signature = String::from_utf8(ctx.result().unwrap()).unwrap().replace('\n', "");
+
+    assert_eq!(signature, concat!(
+        "PKFfDUdly1b3s0NLEQG+cUcqdABqERM1y0bDUdeCRbhaCblCtlRoFQUq/Q9mmFLoboIdz3/4Pyn2PlcJxmMRMJRBzArMr1yjMwUWCKT8SoQ9q2",
+        "Sgxf6y9j01NNDtccbXYoo/rnpfsY99FRxDQZXGBxnPoEPisDc2dx5zCf24EEI=",
+    ));
+}
+
+
+#[test]
+fn test_transform_context_refuses_mismatched_methods()
+{
+    let key = XmlSecKey::from_file("tests/resources/key.pem", XmlSecKeyFormat::Pem, None)
+        .expect("Failed to properly load key for test");
+
+    let mut ctx = XmlSecTransformContext::new()
+        .expect("Failed to create transform context");
+
+    assert!(ctx.append_digest(XmlSecSignatureMethod::RsaSha1).is_err(), "RSA-SHA1 is no digest");
+    assert!(ctx.append_signature(XmlSecSignatureMethod::Sha1, &key).is_err(), "SHA1 is no signature");
+}
+
+
+#[test]
+fn test_transform_context_empty_passes_through()
+{
+    let mut ctx = XmlSecTransformContext::new()
+        .expect("Failed to create transform context");
+
+    ctx.binary_execute(b"Hello world")
+        .expect("Failed to execute empty transform chain");
+
+    assert_eq!(ctx.result(), Some(b"Hello world".to_vec()));
+}
+
+
+struct Reverse
+{
+    href:  &'static str,
+    calls: &'static AtomicUsize,
+}
+
+impl XmlSecCustomTransform for Reverse
+{
+    fn name(&self) -> &str
+    {
+        "reverse"
+    }
+
+    fn href(&self) -> &str
+    {
+        self.href
+    }
+
+    fn execute_binary(&self, input: &[u8]) -> XmlSecResult<Vec<u8>>
+    {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+
+        Ok(input.iter().rev().copied().collect())
+    }
+}
+
+
+struct Identity
+{
+    calls: &'static AtomicUsize,
+}
+
+impl XmlSecCustomTransform for Identity
+{
+    fn name(&self) -> &str
+    {
+        "identity"
+    }
+
+    fn href(&self) -> &str
+    {
+        "urn:test:identity"
+    }
+
+    fn is_xml(&self) -> bool
+    {
+        true
+    }
+
+    unsafe fn execute_xml(
+        &self,
+        nodes: *mut xmlsec::bindings::xmlSecNodeSet,
+    ) -> XmlSecResult<*mut xmlsec::bindings::xmlSecNodeSet>
+    {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+
+        Ok(nodes)
+    }
+}
+
+
+#[test]
+fn test_custom_binary_transform()
+{
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    let reverse = XmlSecCustomTransformId::register(Reverse {href: "urn:test:reverse", calls: &CALLS})
+        .expect("Failed to register custom transform");
+
+    assert_eq!(reverse.href(), "urn:test:reverse");
+
+    let mut ctx = XmlSecTransformContext::new()
+        .expect("Failed to create transform context");
+    ctx.append_custom(reverse).expect("Failed to append custom transform");
+    ctx.append_base64_encode().expect("Failed to append base64 encoding");
+
+    ctx.binary_execute(b"abcdef")
+        .expect("Failed to execute custom transform");
+
+    assert_eq!(ctx.result(), Some(b"ZmVkY2Jh".to_vec()));
+    assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+
+    assert!(
+        XmlSecCustomTransformId::register(Reverse {href: "urn:test:reverse", calls: &CALLS}).is_err(),
+        "Registering the same href twice should fail"
+    );
+}
+
+
+#[test]
+fn test_custom_xml_transform()
+{
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    let identity = XmlSecCustomTransformId::register(Identity {calls: &CALLS})
+        .expect("Failed to register custom transform");
+
+    let doc = XmlParser::default()
+        .parse_string(DOCUMENT)
+        .expect("Failed to parse document");
+
+    let mut ctx = XmlSecTransformContext::new()
+        .expect("Failed to create transform context");
+    ctx.append_custom(identity).expect("Failed to append custom transform");
+    ctx.append_digest(XmlSecSignatureMethod::Sha1).expect("Failed to append digest");
+
+    ctx.xml_execute_document(&doc)
+        .expect("Failed to execute custom transform");
+
+    let mut plain = XmlSecTransformContext::new()
+        .expect("Failed to create transform context");
+    plain.append_digest(XmlSecSignatureMethod::Sha1).expect("Failed to append digest");
+
+    plain.xml_execute_document(&doc)
+        .expect("Failed to digest document");
+
+    assert_eq!(ctx.result(), plain.result(), "Identity transform should not alter the digest");
+    assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+}
+
+
+#[test]
+fn test_custom_transform_in_signature()
+{
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    let reverse = XmlSecCustomTransformId::register(Reverse {href: "urn:test:reverse-signed", calls: &CALLS})
+        .expect("Failed to register custom transform");
+
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load document to be signed");
+
+    doc.template()
+        .reference(XmlSecTemplateReference::new("").enveloped(true).transform(reverse))
+        .done()
+        .expect("Failed to build signature template");
+
+    assert!(doc.to_string().contains("Algorithm=\"urn:test:reverse-signed\""));
+
+    let mut signer = XmlSecSignatureContext::new()
+        .expect("Failed to create signature context");
+    signer.insert_key(load_test_key());
+
+    signer.sign_document(&doc)
+        .expect("Failed to sign document");
+
+    let mut verifier = XmlSecSignatureContext::new()
+        .expect("Failed to create signature context");
+    verifier.insert_key(load_test_key());
+
+    assert!(verifier.verify_document(&doc).expect("Failed to verify document"));
+    assert_eq!(CALLS.load(Ordering::SeqCst), 2, "Transform should run once when signing and once when verifying");
+
+    // the same signature with an unknown transform cannot be verified
+    let unknown = XmlParser::default()
+        .parse_string(&doc.to_string().replace("urn:test:reverse-signed", "urn:test:unregistered"))
+        .expect("Failed to parse altered document");
+
+    let mut verifier = XmlSecSignatureContext::new()
+        .expect("Failed to create signature context");
+    verifier.insert_key(load_test_key());
+
+    assert!(!matches!(verifier.verify_document(&unknown), Ok(true)));
+}
+
+
+#[test]
+fn test_custom_transform_enabled_on_transform_context()
+{
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    let reverse = XmlSecCustomTransformId::register(Reverse {href: "urn:test:reverse-enabled", calls: &CALLS})
+        .expect("Failed to register custom transform");
+
+    let mut ctx = XmlSecTransformContext::new()
+        .expect("Failed to create transform context");
+
+    ctx.enable_transform(reverse).expect("Failed to enable custom transform");
+
+    ctx.append_custom(reverse).expect("Enabled custom transform should be accepted");
+
+    assert!(
+        matches!(ctx.append_base64_encode(), Err(XmlSecError::TransformError(_))),
+        "Transforms left out of the enabled ones should be refused"
+    );
+}
+
+
+#[test]
+fn test_custom_transform_reference_allowlist()
+{
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    let reverse = XmlSecCustomTransformId::register(Reverse {href: "urn:test:reverse-allowlist", calls: &CALLS})
+        .expect("Failed to register custom transform");
+
+    let doc = XmlParser::default()
+        .parse_file("tests/resources/sign2-doc.xml")
+        .expect("Failed to load document to be signed");
+
+    doc.template()
+        .reference(XmlSecTemplateReference::new("").enveloped(true).transform(reverse))
+        .done()
+        .expect("Failed to build signature template");
+
+    let mut signer = XmlSecSignatureContext::new()
+        .expect("Failed to create signature context");
+    signer.insert_key(load_test_key());
+
+    signer.sign_document(&doc)
+        .expect("Failed to sign document");
+
+    let enveloped = unsafe { xmlsec::bindings::xmlSecTransformEnvelopedGetKlass() };
+
+    // allowlist including the custom transform
+    let mut verifier = XmlSecSignatureContext::new()
+        .expect("Failed to create signature context");
+    verifier.insert_key(load_test_key());
+
+    verifier.enable_reference_transform(enveloped).expect("Failed to enable enveloped transform");
+    verifier.enable_reference_transform(XmlSecSignatureMethod::Sha1).expect("Failed to enable digest");
+    verifier.enable_reference_transform(reverse).expect("Failed to enable custom transform");
+
+    assert!(verifier.verify_document(&doc).expect("Failed to verify document"));
+
+    // allowlist leaving the custom transform out
+    let mut verifier = XmlSecSignatureContext::new()
+        .expect("Failed to create signature context");
+    verifier.insert_key(load_test_key());
+
+    verifier.enable_reference_transform(enveloped).expect("Failed to enable enveloped transform");
+    verifier.enable_reference_transform(XmlSecSignatureMethod::Sha1).expect("Failed to enable digest");
+
+    assert!(
+        !matches!(verifier.verify_document(&doc), Ok(true)),
+        "Signature using a transform left out of the allowlist must not verify"
+    );
+
+    assert_eq!(CALLS.load(Ordering::SeqCst), 2, "Transform should only run when signing and when allowed");
+}
+
+
+fn load_test_key() -> XmlSecKey
+{
+    XmlSecKey::from_file("tests/resources/key.pem", XmlSecKeyFormat::Pem, None)
+        .expect("Failed to properly load key for test")
+}
+
+
+fn hex(bytes: Vec<u8>) -> String
+{
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}